@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use lud::message::Message;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(message) = Message::from_wire(data) {
+        let _ = message.to_string();
+        let _ = message.to_wire();
+    }
+});
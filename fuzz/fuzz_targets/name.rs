@@ -0,0 +1,15 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use lud::extract_name;
+
+// The first octet picks where in the rest the name starts, so that
+// compression pointers have something before them to point to.
+fuzz_target!(|data: &[u8]| {
+    if let Some((&start, buf)) = data.split_first() {
+        if let Ok((name, end)) = extract_name(buf, start as usize % (buf.len() + 1)) {
+            assert!(end <= buf.len());
+            assert!(name.iter().map(|label| label.len() + 1).sum::<usize>() < 255);
+        }
+    }
+});
@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use lud::pcap;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(captured) = pcap::read(data) {
+        for packet in captured.iter() {
+            let _ = packet.message();
+        }
+    }
+});
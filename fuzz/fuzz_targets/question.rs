@@ -0,0 +1,10 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use lud::question::Question;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((question, _)) = Question::from_wire(data, 0) {
+        let _ = question.to_string();
+    }
+});
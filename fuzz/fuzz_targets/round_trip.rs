@@ -0,0 +1,17 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use lud::message::Message;
+
+// Whatever parses must survive being written out and parsed again, and
+// come back as the same message.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(message) = Message::from_wire(data) {
+        let wire = message.to_wire();
+        let reparsed = match Message::from_wire(&wire) {
+            Ok(reparsed) => reparsed,
+            Err(e) => panic!("cannot parse what we wrote: {}", e),
+        };
+        assert_eq!(reparsed, message);
+    }
+});
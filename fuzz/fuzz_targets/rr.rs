@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use lud::rr::RR;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((rr, _)) = RR::from_wire(data, 0) {
+        let _ = rr.to_string();
+        let _ = rr.rdata().to_presentation();
+        let _ = rr.to_canonical_wire();
+    }
+});
@@ -0,0 +1,266 @@
+/* Many lookups from one input, one per line:
+ *
+ *   name [type] [@server]
+ *
+ * sent concurrently with a limit on how many are outstanding at once. */
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::question::QType;
+use crate::response::Response;
+
+pub struct Query {
+    /// Line number in the input, from 1.
+    pub line: usize,
+    pub name: String,
+    pub qtype: String,
+    /// The server named on the line, with port, if any.
+    pub server: Option<String>,
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.name, self.qtype)?;
+        if let Some(server) = &self.server {
+            write!(f, " @{}", server)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct BatchError {
+    pub line: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+/// Parse the queries of a batch; blank lines and lines starting with ';'
+/// or '#' are skipped.  Lines without a type ask for `default_qtype`.
+pub fn parse(text: &str, default_qtype: &str) -> Result<Vec<Query>, BatchError> {
+    let mut queries = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+
+        let error = |reason: String| BatchError {
+            line: line_no,
+            reason,
+        };
+        let mut name = None;
+        let mut qtype = None;
+        let mut server = None;
+        for token in line.split_whitespace() {
+            match token.strip_prefix('@') {
+                Some(_) if server.is_some() => {
+                    return Err(error("more than one server".to_string()))
+                }
+                Some("") => return Err(error("empty server".to_string())),
+                Some(host) => server = Some(host.to_string() + ":53"),
+                None if name.is_none() => name = Some(token.to_string()),
+                None if qtype.is_some() => return Err(error(format!("unexpected \"{}\"", token))),
+                None => {
+                    let upper = token.to_uppercase();
+                    if QType::try_from(upper.clone()).is_err() {
+                        return Err(error(format!("unknown type {}", token)));
+                    }
+                    qtype = Some(upper);
+                }
+            }
+        }
+        let name = name.ok_or_else(|| error("no name to look up".to_string()))?;
+
+        queries.push(Query {
+            line: line_no,
+            name,
+            qtype: qtype.unwrap_or_else(|| default_qtype.to_string()),
+            server,
+        });
+    }
+    Ok(queries)
+}
+
+/// Look up every query, at most `in_flight` at a time, and hand each
+/// result to `emit`.  With `ordered` the results come in input order,
+/// otherwise as soon as they arrive.
+pub fn run<L, F>(
+    queries: Vec<Query>,
+    server: &str,
+    in_flight: usize,
+    ordered: bool,
+    lookup: L,
+    mut emit: F,
+) where
+    L: Fn(&Query, &str) -> io::Result<Response> + Send + Sync + 'static,
+    F: FnMut(&Query, io::Result<Response>),
+{
+    let total = queries.len();
+    let queries = Arc::new(queries);
+    let next = Arc::new(Mutex::new(0));
+    let lookup = Arc::new(lookup);
+    let (sender, receiver) = mpsc::channel();
+
+    let mut workers = Vec::new();
+    for _ in 0..in_flight.clamp(1, total.max(1)) {
+        let (queries, next, lookup, sender) = (
+            Arc::clone(&queries),
+            Arc::clone(&next),
+            Arc::clone(&lookup),
+            sender.clone(),
+        );
+        let server = server.to_string();
+        workers.push(thread::spawn(move || loop {
+            let idx = {
+                let mut next = next.lock().unwrap();
+                let idx = *next;
+                *next += 1;
+                idx
+            };
+            let query: &Query = match queries.get(idx) {
+                Some(query) => query,
+                None => break,
+            };
+            let result = lookup(query, query.server.as_deref().unwrap_or(&server));
+            if sender.send((idx, result)).is_err() {
+                break;
+            }
+        }));
+    }
+    drop(sender);
+
+    let mut pending = BTreeMap::new();
+    let mut emitted = 0;
+    for (idx, result) in receiver.iter() {
+        if !ordered {
+            emit(&queries[idx], result);
+            continue;
+        }
+        pending.insert(idx, result);
+        while let Some(result) = pending.remove(&emitted) {
+            emit(&queries[emitted], result);
+            emitted += 1;
+        }
+    }
+    for worker in workers {
+        let _ = worker.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use crate::response::Transport;
+    use std::sync::Condvar;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn batch_parse() {
+        let queries = parse(
+            "example.com\n\
+             ; a comment\n\
+             \n\
+             example.net mx @192.0.2.53\n\
+             @192.0.2.1 example.org\n",
+            "A",
+        )
+        .unwrap();
+        let parsed: Vec<String> = queries
+            .iter()
+            .map(|q| format!("{} {}", q.line, q))
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![
+                "1 example.com A",
+                "4 example.net MX @192.0.2.53:53",
+                "5 example.org A @192.0.2.1:53",
+            ]
+        );
+
+        let err = parse("example.com A AAAA\n", "A").err().unwrap();
+        assert_eq!(err.to_string(), "line 1: unexpected \"AAAA\"");
+        let err = parse("ok\nexample.com BOGUS\n", "A").err().unwrap();
+        assert_eq!(err.to_string(), "line 2: unknown type BOGUS");
+    }
+
+    fn fake_lookup(query: &Query, server: &str) -> io::Result<Response> {
+        let mut reply = Message::new(crate::parse_name(query.name.clone()), query.qtype.clone());
+        reply.id = query.line as u16;
+        if server == "unreachable:53" {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "timed out"));
+        }
+        Response::new(
+            &reply.to_wire(),
+            server.to_string(),
+            Transport::Udp,
+            SystemTime::now(),
+            Duration::from_millis(1),
+        )
+    }
+
+    /// The lines of the queries in the order they are emitted, when later
+    /// queries finish first.
+    fn lines(ordered: bool) -> Vec<usize> {
+        let queries = parse("a.example\nb.example\nc.example @unreachable\n", "A").unwrap();
+        let total = queries.len();
+        // a query is answered once all the later ones have been released:
+        // by being emitted, or, when in order and nothing is emitted before
+        // the first query, by being answered
+        let released = Arc::new((Mutex::new(0), Condvar::new()));
+        let waiting = Arc::clone(&released);
+        let lookup = move |query: &Query, server: &str| {
+            let (count, changed) = &*waiting;
+            let mut count = changed
+                .wait_while(count.lock().unwrap(), |count| *count < total - query.line)
+                .unwrap();
+            if ordered {
+                *count += 1;
+                changed.notify_all();
+            }
+            fake_lookup(query, server)
+        };
+
+        let mut seen = Vec::new();
+        run(
+            queries,
+            "192.0.2.53:53",
+            3,
+            ordered,
+            lookup,
+            |query, result| {
+                match result {
+                    Ok(reply) => assert_eq!(reply.message.id as usize, query.line),
+                    Err(_) => assert_eq!(query.line, 3),
+                }
+                seen.push(query.line);
+                if !ordered {
+                    let (count, changed) = &*released;
+                    *count.lock().unwrap() += 1;
+                    changed.notify_all();
+                }
+            },
+        );
+        seen
+    }
+
+    #[test]
+    fn batch_order() {
+        assert_eq!(lines(true), vec![1, 2, 3]);
+        assert_eq!(lines(false), vec![3, 2, 1]);
+    }
+}
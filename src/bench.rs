@@ -0,0 +1,269 @@
+/* Load generation: replay a list of queries against a server at a steady
+ * rate and summarize how it coped. */
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::batch::Query;
+use crate::message::Message;
+use crate::transport::Engine;
+
+pub enum Outcome {
+    Reply {
+        rcode: String,
+        truncated: bool,
+    },
+    Timeout,
+    /// The send failed or the reply could not be parsed.
+    Error(String),
+}
+
+pub struct Sample {
+    pub elapsed: Duration,
+    pub outcome: Outcome,
+}
+
+pub struct Report {
+    pub sent: usize,
+    /// From the first query sent to the last reply or timeout.
+    pub wall: Duration,
+    /// Round trip times of the replies, shortest first.
+    pub latencies: Vec<Duration>,
+    pub rcodes: BTreeMap<String, usize>,
+    pub truncated: usize,
+    pub timeouts: usize,
+    pub errors: usize,
+}
+
+impl Report {
+    pub fn from_samples(samples: Vec<Sample>, wall: Duration) -> Report {
+        let mut report = Report {
+            sent: samples.len(),
+            wall,
+            latencies: Vec::new(),
+            rcodes: BTreeMap::new(),
+            truncated: 0,
+            timeouts: 0,
+            errors: 0,
+        };
+        for sample in samples {
+            match sample.outcome {
+                Outcome::Reply { rcode, truncated } => {
+                    report.latencies.push(sample.elapsed);
+                    *report.rcodes.entry(rcode).or_default() += 1;
+                    if truncated {
+                        report.truncated += 1;
+                    }
+                }
+                Outcome::Timeout => report.timeouts += 1,
+                Outcome::Error(_) => report.errors += 1,
+            }
+        }
+        report.latencies.sort();
+        report
+    }
+
+    /// The nearest-rank percentile of the reply latencies.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let rank = (p / 100.0 * self.latencies.len() as f64).ceil() as usize;
+        Some(self.latencies[rank.clamp(1, self.latencies.len()) - 1])
+    }
+}
+
+fn percent(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / whole as f64
+    }
+}
+
+fn millis(duration: Option<Duration>) -> String {
+    match duration {
+        Some(duration) => format!("{:.1}", duration.as_secs_f64() * 1000.0),
+        None => "-".to_string(),
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let replies = self.latencies.len();
+        let secs = self.wall.as_secs_f64();
+        let rate = if secs > 0.0 {
+            self.sent as f64 / secs
+        } else {
+            0.0
+        };
+        writeln!(
+            f,
+            "queries sent:\t{} in {:.2}s ({:.1} qps)",
+            self.sent, secs, rate
+        )?;
+        writeln!(
+            f,
+            "replies:\t{} ({:.1}%)",
+            replies,
+            percent(replies, self.sent)
+        )?;
+        writeln!(
+            f,
+            "timeouts:\t{} ({:.1}%)",
+            self.timeouts,
+            percent(self.timeouts, self.sent)
+        )?;
+        writeln!(f, "errors:\t\t{}", self.errors)?;
+        writeln!(
+            f,
+            "truncated:\t{} ({:.1}% of replies)",
+            self.truncated,
+            percent(self.truncated, replies)
+        )?;
+        writeln!(
+            f,
+            "latency (ms):\tmin {}  p50 {}  p90 {}  p99 {}  max {}",
+            millis(self.latencies.first().copied()),
+            millis(self.percentile(50.0)),
+            millis(self.percentile(90.0)),
+            millis(self.percentile(99.0)),
+            millis(self.latencies.last().copied())
+        )?;
+        write!(f, "response codes:")?;
+        for (rcode, count) in self.rcodes.iter() {
+            write!(f, "\n  {}\t{}", rcode, count)?;
+        }
+        Ok(())
+    }
+}
+
+fn measure(engine: &Engine, query: &Query, server: &str) -> Sample {
+    let started = Instant::now();
+    let result = engine.exchange(query.name.clone(), query.qtype.clone(), server);
+    let elapsed = started.elapsed();
+
+    let outcome = match result {
+        Ok(reply) => match Message::from_wire(&reply) {
+            Ok(message) => Outcome::Reply {
+                rcode: format!("{:?}", message.meta.rcode),
+                truncated: message.meta.tc,
+            },
+            Err(e) => Outcome::Error(e.to_string()),
+        },
+        Err(ref e) if e.kind() == io::ErrorKind::TimedOut => Outcome::Timeout,
+        Err(e) => Outcome::Error(e.to_string()),
+    };
+    Sample { elapsed, outcome }
+}
+
+/// Send `count` queries, cycling through `queries`, at `qps` queries per
+/// second with at most `in_flight` outstanding.  When every query slot is
+/// busy, sending falls behind the target rate; the report shows the rate
+/// achieved.
+pub fn run(
+    engine: Arc<Engine>,
+    queries: Vec<Query>,
+    server: &str,
+    qps: f64,
+    count: usize,
+    in_flight: usize,
+) -> Report {
+    let queries = Arc::new(queries);
+    let (job_sender, job_receiver) = mpsc::sync_channel::<usize>(0);
+    let job_receiver = Arc::new(Mutex::new(job_receiver));
+    let (sample_sender, sample_receiver) = mpsc::channel();
+
+    let mut workers = Vec::new();
+    for _ in 0..in_flight.max(1) {
+        let (engine, queries, jobs, samples) = (
+            Arc::clone(&engine),
+            Arc::clone(&queries),
+            Arc::clone(&job_receiver),
+            sample_sender.clone(),
+        );
+        let server = server.to_string();
+        workers.push(thread::spawn(move || loop {
+            let job = jobs.lock().unwrap().recv();
+            let query = match job {
+                Ok(idx) => &queries[idx % queries.len()],
+                Err(_) => break,
+            };
+            let server = query.server.as_deref().unwrap_or(&server);
+            if samples.send(measure(&engine, query, server)).is_err() {
+                break;
+            }
+        }));
+    }
+    drop(sample_sender);
+
+    let started = Instant::now();
+    let interval = Duration::from_secs_f64(1.0 / qps);
+    for idx in 0..count {
+        let due = started + interval * idx as u32;
+        let now = Instant::now();
+        if due > now {
+            thread::sleep(due - now);
+        }
+        // blocks until a worker is free
+        if job_sender.send(idx).is_err() {
+            break;
+        }
+    }
+    drop(job_sender);
+
+    let samples: Vec<Sample> = sample_receiver.iter().collect();
+    let wall = started.elapsed();
+    for worker in workers {
+        let _ = worker.join();
+    }
+    Report::from_samples(samples, wall)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(ms: u64, rcode: &str, truncated: bool) -> Sample {
+        Sample {
+            elapsed: Duration::from_millis(ms),
+            outcome: Outcome::Reply {
+                rcode: rcode.to_string(),
+                truncated,
+            },
+        }
+    }
+
+    #[test]
+    fn bench_report() {
+        let mut samples: Vec<Sample> = (1..=97).map(|ms| reply(ms, "NoError", false)).collect();
+        samples.push(reply(200, "NameError", true));
+        samples.push(Sample {
+            elapsed: Duration::from_secs(2),
+            outcome: Outcome::Timeout,
+        });
+        samples.push(Sample {
+            elapsed: Duration::from_millis(1),
+            outcome: Outcome::Error("bad reply".to_string()),
+        });
+
+        let report = Report::from_samples(samples, Duration::from_secs(1));
+        assert_eq!(report.percentile(50.0), Some(Duration::from_millis(49)));
+        assert_eq!(report.percentile(98.0), Some(Duration::from_millis(97)));
+        assert_eq!(report.percentile(100.0), Some(Duration::from_millis(200)));
+
+        let text = report.to_string();
+        assert!(text.contains("queries sent:\t100 in 1.00s (100.0 qps)\n"));
+        assert!(text.contains("replies:\t98 (98.0%)\n"));
+        assert!(text.contains("timeouts:\t1 (1.0%)\n"));
+        assert!(text.contains("errors:\t\t1\n"));
+        assert!(text.contains("truncated:\t1 (1.0% of replies)\n"));
+        assert!(text.contains("latency (ms):\tmin 1.0  p50 49.0  p90 89.0  p99 200.0  max 200.0\n"));
+        assert!(text.ends_with("response codes:\n  NameError\t1\n  NoError\t97"));
+    }
+}
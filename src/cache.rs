@@ -0,0 +1,491 @@
+/* Answers kept in memory for as long as their TTLs allow, so that repeated
+ * lookups need not go to the network.  Records are kept by RRset, and
+ * negative answers by the SOA that came with them (RFC 2308). */
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::time::Instant;
+
+use crate::message::{Message, RCode, QR};
+use crate::question::QType;
+use crate::rdata::RData;
+use crate::rr::{Class, RRType, RR};
+use crate::rrset::RRset;
+use crate::ttl::Ttl;
+
+/// How many CNAMEs a cached answer may follow before giving up.
+const MAX_CNAME_CHAIN: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    /// Lowercased, without the empty root label.
+    name: Vec<String>,
+    /// None for a name that does not exist at all, whatever the type.
+    rrtype: Option<RRType>,
+    class: Class,
+}
+
+impl Key {
+    fn new(name: &[String], rrtype: Option<RRType>, class: Class) -> Key {
+        Key {
+            name: name
+                .iter()
+                .filter(|l| !l.is_empty())
+                .map(|l| l.to_lowercase())
+                .collect(),
+            rrtype,
+            class,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Data {
+    Records(RRset),
+    /// NXDOMAIN or NODATA, with the SOA of the zone that said so.
+    Negative(RR),
+}
+
+#[derive(Debug)]
+struct Entry {
+    data: Data,
+    expires: Instant,
+    /// When the entry was last read or written, for eviction.
+    used: u64,
+}
+
+/// What a cached entry holds once its TTLs have been brought up to date.
+enum Found {
+    Records(Vec<RR>),
+    Negative(RR),
+}
+
+pub struct Cache {
+    max_entries: usize,
+    entries: HashMap<Key, Entry>,
+    /// Keys by when they were last used, least recent first.
+    usage: BTreeMap<u64, Key>,
+    clock: u64,
+}
+
+impl Cache {
+    /// A cache holding at most `max_entries` RRsets and negative answers;
+    /// when full, the least recently used one makes way for a new one.
+    pub fn new(max_entries: usize) -> Cache {
+        Cache {
+            max_entries,
+            entries: HashMap::new(),
+            usage: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.usage.clear();
+    }
+
+    /// Keep what `reply` says about its question.
+    pub fn insert(&mut self, reply: &Message) {
+        self.insert_at(reply, Instant::now());
+    }
+
+    /// A reply to a query for `name`, built from the cache, with the TTLs
+    /// reduced by the time spent there; None if the cache can't answer.
+    pub fn get(&mut self, name: &[String], rrtype: RRType, class: Class) -> Option<Message> {
+        self.get_at(name, rrtype, class, Instant::now())
+    }
+
+    fn insert_at(&mut self, reply: &Message, now: Instant) {
+        // only whole answers to a single question are kept
+        if reply.meta.tc || reply.question.len() != 1 {
+            return;
+        }
+        if reply.meta.rcode != RCode::NoError && reply.meta.rcode != RCode::NameError {
+            return;
+        }
+        let question = &reply.question[0];
+        let rrtype = match question.qtype {
+            QType::RRType(rrtype) => rrtype,
+            _ => return,
+        };
+        let class = match Class::try_from(question.qclass) {
+            Ok(class) => class,
+            Err(_) => return,
+        };
+
+        // Follow the CNAMEs in the answer from the question to the name it
+        // is really about.
+        let rrsets = RRset::group(reply.answer.clone());
+        let mut chain = vec![Key::new(&question.qname, None, class).name];
+        for _ in 0..MAX_CNAME_CHAIN {
+            if rrtype == RRType::CNAME {
+                break;
+            }
+            let name = &chain[chain.len() - 1];
+            let target = rrsets
+                .iter()
+                .find_map(|rrset| match rrset.records()[0].rdata() {
+                    RData::CNAME(cname) if Key::new(rrset.name(), None, class).name == *name => {
+                        Some(Key::new(cname.cname(), None, class).name)
+                    }
+                    _ => None,
+                });
+            match target {
+                Some(target) if !chain.contains(&target) => chain.push(target),
+                _ => break,
+            }
+        }
+
+        // The authority and additional sections are not trusted enough to
+        // answer from (RFC 2181 §5.4.1), and neither is anything in the
+        // answer that is not about a name on the chain: a server could
+        // otherwise slip in records for names it has no say over.
+        for rrset in rrsets.iter() {
+            let key = Key::new(rrset.name(), Some(rrset.rrtype()), rrset.class());
+            if key.class != class || !chain.contains(&key.name) {
+                continue;
+            }
+            let ttl = rrset.ttl();
+            self.store(key, Data::Records(rrset.clone()), ttl, now);
+        }
+
+        let name = chain.pop().unwrap();
+        let answered = rrsets.iter().any(|rrset| {
+            rrset.rrtype() == rrtype && Key::new(rrset.name(), None, class).name == name
+        });
+        if answered {
+            return;
+        }
+
+        // A negative answer without a SOA can't be cached (RFC 2308 §5).
+        let soa = reply.authority.iter().find(|rr| rr.rrtype() == RRType::SOA);
+        if let (Some(soa), Some(ttl)) = (soa, reply.negative_ttl()) {
+            let key = match reply.meta.rcode {
+                RCode::NameError => Key::new(&name, None, class),
+                _ => Key::new(&name, Some(rrtype), class),
+            };
+            self.store(key, Data::Negative(soa.clone().with_ttl(ttl)), ttl, now);
+        }
+    }
+
+    fn get_at(
+        &mut self,
+        name: &[String],
+        rrtype: RRType,
+        class: Class,
+        now: Instant,
+    ) -> Option<Message> {
+        let mut reply = Message::new(name.to_vec(), rrtype.to_string()).with_class(class);
+        reply.meta.qr = QR::R;
+        reply.meta.ra = true;
+
+        let mut name = name.to_vec();
+        for _ in 0..MAX_CNAME_CHAIN {
+            match self.fetch(&Key::new(&name, Some(rrtype), class), now) {
+                Some(Found::Records(records)) => {
+                    reply.answer.extend(records);
+                    return Some(finish(reply, RCode::NoError, None));
+                }
+                Some(Found::Negative(soa)) => {
+                    return Some(finish(reply, RCode::NoError, Some(soa)))
+                }
+                None => (),
+            }
+            if let Some(Found::Negative(soa)) = self.fetch(&Key::new(&name, None, class), now) {
+                return Some(finish(reply, RCode::NameError, Some(soa)));
+            }
+            if rrtype == RRType::CNAME {
+                return None;
+            }
+            match self.fetch(&Key::new(&name, Some(RRType::CNAME), class), now) {
+                Some(Found::Records(records)) => {
+                    name = match records[0].rdata() {
+                        RData::CNAME(cname) => cname.cname().to_vec(),
+                        _ => return None,
+                    };
+                    reply.answer.extend(records);
+                }
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    /// The entry for `key` with its TTLs counted down to `now`, dropping it
+    /// if it has expired.
+    fn fetch(&mut self, key: &Key, now: Instant) -> Option<Found> {
+        let expires = self.entries.get(key)?.expires;
+        if expires <= now {
+            if let Some(entry) = self.entries.remove(key) {
+                self.usage.remove(&entry.used);
+            }
+            return None;
+        }
+        let ttl = Ttl::from_secs((expires - now).as_secs() as u32);
+
+        self.clock += 1;
+        let entry = self.entries.get_mut(key)?;
+        self.usage.remove(&entry.used);
+        self.usage.insert(self.clock, key.clone());
+        entry.used = self.clock;
+
+        Some(match &entry.data {
+            Data::Records(rrset) => Found::Records(
+                rrset
+                    .records()
+                    .iter()
+                    .map(|rr| rr.clone().with_ttl(ttl))
+                    .collect(),
+            ),
+            Data::Negative(soa) => Found::Negative(soa.clone().with_ttl(ttl)),
+        })
+    }
+
+    fn store(&mut self, key: Key, data: Data, ttl: Ttl, now: Instant) {
+        if ttl.as_secs() == 0 || self.max_entries == 0 {
+            return;
+        }
+        if let Some(old) = self.entries.remove(&key) {
+            self.usage.remove(&old.used);
+        }
+        while self.entries.len() >= self.max_entries {
+            let oldest = match self.usage.keys().next() {
+                Some(&used) => used,
+                None => break,
+            };
+            if let Some(evicted) = self.usage.remove(&oldest) {
+                self.entries.remove(&evicted);
+            }
+        }
+
+        self.clock += 1;
+        self.usage.insert(self.clock, key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                data,
+                expires: now + ttl.as_duration(),
+                used: self.clock,
+            },
+        );
+    }
+}
+
+fn finish(mut reply: Message, rcode: RCode, soa: Option<RR>) -> Message {
+    reply.meta.rcode = rcode;
+    reply.ancount = reply.answer.len() as u16;
+    reply.authority.extend(soa);
+    reply.nscount = reply.authority.len() as u16;
+    reply
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn labels(name: &str) -> Vec<String> {
+        name.split('.').map(String::from).collect()
+    }
+
+    fn record(text: &str) -> RR {
+        crate::zone::parse_str(text, ".").unwrap().remove(0)
+    }
+
+    fn reply(
+        name: &str,
+        rrtype: RRType,
+        rcode: RCode,
+        answer: &[&str],
+        authority: &[&str],
+    ) -> Message {
+        let mut reply = Message::new(labels(name), rrtype.to_string());
+        reply.meta.qr = QR::R;
+        reply.meta.rcode = rcode;
+        reply.answer = answer.iter().map(|text| record(text)).collect();
+        reply.ancount = reply.answer.len() as u16;
+        reply.authority = authority.iter().map(|text| record(text)).collect();
+        reply.nscount = reply.authority.len() as u16;
+        reply
+    }
+
+    const SOA: &str =
+        "example.com. 3600 IN SOA ns1.example.com. hostmaster.example.com. 1 7200 3600 1209600 300";
+
+    #[test]
+    fn cache_records() {
+        let mut cache = Cache::new(16);
+        let start = Instant::now();
+        cache.insert_at(
+            &reply(
+                "www.example.com",
+                RRType::A,
+                RCode::NoError,
+                &[
+                    "www.example.com. 300 IN CNAME web.example.com.",
+                    "web.example.com. 60 IN A 192.0.2.1",
+                    "web.example.com. 120 IN A 192.0.2.2",
+                ],
+                &[],
+            ),
+            start,
+        );
+        assert_eq!(cache.len(), 2);
+
+        // followed through the CNAME, case-insensitively, with TTLs counted down
+        let later = start + Duration::from_secs(20);
+        let cached = cache
+            .get_at(&labels("WWW.example.com."), RRType::A, Class::IN, later)
+            .unwrap();
+        assert_eq!(cached.meta.rcode, RCode::NoError);
+        assert_eq!(cached.ancount, 3);
+        assert_eq!(cached.answer[0].ttl(), Ttl::from_secs(280));
+        assert_eq!(cached.answer[1].ttl(), Ttl::from_secs(40));
+        assert_eq!(cached.answer[2].rdata().to_presentation(), "192.0.2.2");
+
+        // the A records expire before the CNAME does
+        let expired = start + Duration::from_secs(60);
+        assert!(cache
+            .get_at(&labels("www.example.com"), RRType::A, Class::IN, expired)
+            .is_none());
+        let cname = cache.get_at(
+            &labels("www.example.com"),
+            RRType::CNAME,
+            Class::IN,
+            expired,
+        );
+        assert_eq!(cname.unwrap().answer[0].ttl(), Ttl::from_secs(240));
+        assert_eq!(cache.len(), 1);
+        assert!(cache
+            .get_at(&labels("www.example.com"), RRType::A, Class::CH, start)
+            .is_none());
+    }
+
+    #[test]
+    fn cache_only_the_chain() {
+        let mut cache = Cache::new(16);
+        let start = Instant::now();
+        cache.insert_at(
+            &reply(
+                "evil.example",
+                RRType::A,
+                RCode::NoError,
+                &[
+                    "evil.example. 300 IN CNAME www.evil.example.",
+                    "www.evil.example. 300 IN A 192.0.2.1",
+                    "bank.example. 86400 IN A 203.0.113.66",
+                    "evil.example. 300 CH A 192.0.2.2",
+                ],
+                &[],
+            ),
+            start,
+        );
+        assert_eq!(cache.len(), 2);
+        assert!(cache
+            .get_at(&labels("bank.example"), RRType::A, Class::IN, start)
+            .is_none());
+        let cached = cache
+            .get_at(&labels("evil.example"), RRType::A, Class::IN, start)
+            .unwrap();
+        assert_eq!(cached.ancount, 2);
+    }
+
+    #[test]
+    fn cache_negative() {
+        let mut cache = Cache::new(16);
+        let start = Instant::now();
+        cache.insert_at(
+            &reply("nx.example.com", RRType::A, RCode::NameError, &[], &[SOA]),
+            start,
+        );
+        cache.insert_at(
+            &reply("example.com", RRType::AAAA, RCode::NoError, &[], &[SOA]),
+            start,
+        );
+        // without a SOA there is nothing to say how long to keep it
+        cache.insert_at(
+            &reply("other.example.com", RRType::A, RCode::NameError, &[], &[]),
+            start,
+        );
+        assert_eq!(cache.len(), 2);
+
+        // NXDOMAIN holds for every type, for the SOA MINIMUM of 300s
+        let later = start + Duration::from_secs(100);
+        let nx = cache
+            .get_at(&labels("nx.example.com"), RRType::MX, Class::IN, later)
+            .unwrap();
+        assert_eq!(nx.meta.rcode, RCode::NameError);
+        assert!(nx.answer.is_empty());
+        assert_eq!(nx.authority[0].ttl(), Ttl::from_secs(200));
+
+        let nodata = cache
+            .get_at(&labels("example.com"), RRType::AAAA, Class::IN, later)
+            .unwrap();
+        assert_eq!(nodata.meta.rcode, RCode::NoError);
+        assert_eq!(nodata.nscount, 1);
+        assert!(cache
+            .get_at(&labels("example.com"), RRType::A, Class::IN, later)
+            .is_none());
+
+        let expired = start + Duration::from_secs(300);
+        assert!(cache
+            .get_at(&labels("nx.example.com"), RRType::A, Class::IN, expired)
+            .is_none());
+    }
+
+    #[test]
+    fn cache_eviction() {
+        let mut cache = Cache::new(2);
+        let start = Instant::now();
+        for name in ["a", "b"].iter() {
+            let text = format!("{}.example.com. 60 IN A 192.0.2.1", name);
+            let name = format!("{}.example.com", name);
+            cache.insert_at(
+                &reply(&name, RRType::A, RCode::NoError, &[&text], &[]),
+                start,
+            );
+        }
+        // reading a makes b the least recently used
+        assert!(cache
+            .get_at(&labels("a.example.com"), RRType::A, Class::IN, start)
+            .is_some());
+        cache.insert_at(
+            &reply(
+                "c.example.com",
+                RRType::A,
+                RCode::NoError,
+                &["c.example.com. 60 IN A 192.0.2.3"],
+                &[],
+            ),
+            start,
+        );
+        assert_eq!(cache.len(), 2);
+        assert!(cache
+            .get_at(&labels("b.example.com"), RRType::A, Class::IN, start)
+            .is_none());
+        assert!(cache
+            .get_at(&labels("a.example.com"), RRType::A, Class::IN, start)
+            .is_some());
+        assert!(cache
+            .get_at(&labels("c.example.com"), RRType::A, Class::IN, start)
+            .is_some());
+
+        // zero TTLs and failures are not kept
+        cache.clear();
+        let mut failed = reply("d.example.com", RRType::A, RCode::ServFail, &[], &[SOA]);
+        cache.insert_at(&failed, start);
+        failed.meta.rcode = RCode::NoError;
+        failed.answer = vec![record("d.example.com. 0 IN A 192.0.2.4")];
+        cache.insert_at(&failed, start);
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,332 @@
+/* Compare the records of a zone file with what an authoritative server
+ * actually serves for each owner name and type in it. */
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+
+use crate::rdata::RData;
+use crate::rr::{RRType, RR};
+use crate::ttl::Ttl;
+use crate::utils::fqdn;
+
+#[derive(Clone)]
+pub struct Record {
+    pub owner: String,
+    pub rrtype: RRType,
+    pub ttl: Ttl,
+    pub rdata: String,
+    // compared instead of the presentation, so that names match whatever
+    // their case
+    canonical: Vec<u8>,
+}
+
+impl Record {
+    fn from_rr(rr: &RR) -> Record {
+        Record {
+            owner: fqdn(rr.name()).to_lowercase(),
+            rrtype: rr.rrtype(),
+            ttl: rr.ttl(),
+            rdata: rr.rdata().to_presentation(),
+            canonical: rr.rdata().to_canonical_wire(),
+        }
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}",
+            self.owner,
+            self.ttl.as_secs(),
+            self.rrtype,
+            self.rdata
+        )
+    }
+}
+
+pub enum Finding {
+    /// In the zone file but not served.
+    Missing(Record),
+    /// Served but not in the zone file.
+    Extra(Record),
+    /// The owner and type exist on both sides with different RDATA.
+    RDataDiffers {
+        expected: Vec<Record>,
+        served: Vec<Record>,
+    },
+    /// Same RDATA, different TTL.
+    TtlDiffers { expected: Record, served_ttl: Ttl },
+    QueryFailed {
+        owner: String,
+        rrtype: RRType,
+        reason: String,
+    },
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Finding::Missing(record) => write!(f, "MISSING\t{}", record),
+            Finding::Extra(record) => write!(f, "EXTRA\t{}", record),
+            Finding::RDataDiffers { expected, served } => {
+                let mut to_write = String::from("RDATA DIFFERS");
+                for record in expected.iter() {
+                    to_write.push_str(&format!("\n  zone:   {}", record));
+                }
+                for record in served.iter() {
+                    to_write.push_str(&format!("\n  served: {}", record));
+                }
+                write!(f, "{}", to_write)
+            }
+            Finding::TtlDiffers {
+                expected,
+                served_ttl,
+            } => write!(
+                f,
+                "TTL DIFFERS\t{} (served TTL {})",
+                expected,
+                served_ttl.as_secs()
+            ),
+            Finding::QueryFailed {
+                owner,
+                rrtype,
+                reason,
+            } => write!(f, "QUERY FAILED\t{}\t{}\t{}", owner, rrtype, reason),
+        }
+    }
+}
+
+type Key = (String, u16);
+
+fn group(records: &[Record]) -> BTreeMap<Key, Vec<Record>> {
+    let mut groups: BTreeMap<Key, Vec<Record>> = BTreeMap::new();
+    for record in records.iter() {
+        groups
+            .entry((record.owner.clone(), u16::from(record.rrtype)))
+            .or_default()
+            .push(record.clone());
+    }
+    groups
+}
+
+/// Compare the expected records of one owner/type with the served ones.
+fn compare_set(expected: &[Record], served: &[Record], findings: &mut Vec<Finding>) {
+    let mut unmatched_served: Vec<&Record> = served.iter().collect();
+    let mut unmatched_expected = Vec::new();
+
+    for record in expected.iter() {
+        match unmatched_served.iter().position(|s| s.canonical == record.canonical) {
+            Some(idx) => {
+                let matched = unmatched_served.remove(idx);
+                if matched.ttl != record.ttl {
+                    findings.push(Finding::TtlDiffers {
+                        expected: record.clone(),
+                        served_ttl: matched.ttl,
+                    });
+                }
+            }
+            None => unmatched_expected.push(record.clone()),
+        }
+    }
+
+    if !unmatched_expected.is_empty() && !unmatched_served.is_empty() {
+        findings.push(Finding::RDataDiffers {
+            expected: unmatched_expected,
+            served: unmatched_served.into_iter().cloned().collect(),
+        });
+    } else {
+        findings.extend(unmatched_expected.into_iter().map(Finding::Missing));
+        findings.extend(unmatched_served.into_iter().cloned().map(Finding::Extra));
+    }
+}
+
+/// Compare zone file records with served records; only owner/type pairs
+/// present in the zone file are considered.
+pub fn compare(expected: &[RR], served: &[RR]) -> Vec<Finding> {
+    let expected = group(&expected.iter().map(Record::from_rr).collect::<Vec<_>>());
+    let served = group(&served.iter().map(Record::from_rr).collect::<Vec<_>>());
+
+    let mut findings = Vec::new();
+    for (key, records) in expected.iter() {
+        let served_records = served.get(key).map(Vec::as_slice).unwrap_or(&[]);
+        compare_set(records, served_records, &mut findings);
+    }
+    findings
+}
+
+/// Query `server` for every owner/type in `records` and compare the
+/// answers with the zone file.
+pub fn check_zone(records: &[RR], server: &str) -> Vec<Finding> {
+    let expected = group(&records.iter().map(Record::from_rr).collect::<Vec<_>>());
+    let mut findings = Vec::new();
+
+    for ((owner, _), zone_records) in expected.iter() {
+        let rrtype = zone_records[0].rrtype;
+        let reply = match crate::lookup(owner.clone(), rrtype.to_string(), server.to_string()) {
+            Ok(reply) => reply,
+            Err(e) => {
+                findings.push(Finding::QueryFailed {
+                    owner: owner.clone(),
+                    rrtype,
+                    reason: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        // delegations and glue come back outside the answer section
+        let served: Vec<Record> = reply
+            .answer
+            .iter()
+            .chain(reply.authority.iter())
+            .chain(reply.additional.iter())
+            .map(Record::from_rr)
+            .filter(|r| &r.owner == owner && r.rrtype == rrtype)
+            .collect();
+        compare_set(zone_records, &served, &mut findings);
+    }
+    findings
+}
+
+/// Find the address of the zone's primary master, from the MNAME of its SOA;
+/// an IPv4 address if it has one, else an IPv6 one.
+pub fn primary_master(records: &[RR], resolver: &str) -> io::Result<String> {
+    let mname = records
+        .iter()
+        .find_map(|rr| match rr.rdata() {
+            RData::SOA(soa) => Some(fqdn(soa.mname())),
+            _ => None,
+        })
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "zone has no SOA record"))?;
+
+    for rrtype in [RRType::A, RRType::AAAA].iter() {
+        let reply = crate::lookup(mname.clone(), rrtype.to_string(), resolver.to_string())?;
+        let address = reply.answer.iter().find_map(|rr| match rr.rdata() {
+            RData::A(data) => Some(format!("{}:53", data.address())),
+            RData::AAAA(data) => Some(format!("[{}]:53", data.address())),
+            _ => None,
+        });
+        if let Some(address) = address {
+            return Ok(address);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no address for {}", mname),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Message, QR};
+    use crate::resolver::{Resolver, ZoneResolver};
+    use crate::zone;
+    use std::net::UdpSocket;
+    use std::thread;
+
+    #[test]
+    fn check_compare() {
+        let expected = zone::parse_str(
+            "$TTL 300\n\
+             @ NS ns1\n\
+             @ NS ns2\n\
+             www A 192.0.2.1\n\
+             www A 192.0.2.2\n\
+             mail MX 10 mx1\n\
+             ftp A 192.0.2.9\n",
+            "example.",
+        )
+        .unwrap();
+        let served = zone::parse_str(
+            "$TTL 300\n\
+             @ NS ns1\n\
+             @ 600 NS ns2\n\
+             www A 192.0.2.1\n\
+             mail MX 20 mx1\n\
+             extra A 192.0.2.3\n",
+            "example.",
+        )
+        .unwrap();
+
+        let findings: Vec<String> = compare(&expected, &served)
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                "TTL DIFFERS\texample.\t300\tNS\tns2.example. (served TTL 600)",
+                "MISSING\tftp.example.\t300\tA\t192.0.2.9",
+                "RDATA DIFFERS\n  zone:   mail.example.\t300\tMX\t10 mx1.example.\
+                 \n  served: mail.example.\t300\tMX\t20 mx1.example.",
+                "MISSING\twww.example.\t300\tA\t192.0.2.2",
+            ]
+        );
+    }
+
+    #[test]
+    fn check_compare_ignores_case() {
+        let expected = zone::parse_str("mail 300 MX 10 MX1.Example.\n", "example.").unwrap();
+        let served = zone::parse_str("mail 300 MX 10 mx1.example.\n", "example.").unwrap();
+        assert!(compare(&expected, &served).is_empty());
+    }
+
+    /// A server that answers every query it receives from `zone`.
+    fn zone_server(zone: &'static str) -> String {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let resolver = ZoneResolver { zone };
+            let mut buf = [0u8; 512];
+            loop {
+                let (received, from) = socket.recv_from(&mut buf).unwrap();
+                let mut reply = Message::from_wire(&buf[..received]).unwrap();
+                let question = &reply.question[0];
+                let rrtype = RRType::from(u16::from(question.qtype));
+                let answer = resolver.resolve(&fqdn(&question.qname), rrtype).unwrap();
+                reply.meta.qr = QR::R;
+                reply.meta.rcode = answer.meta.rcode;
+                reply.ancount = answer.ancount;
+                reply.answer = answer.answer;
+                socket.send_to(&reply.to_wire(), from).unwrap();
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn check_zone_against_server() {
+        let server = zone_server(
+            "example. 300 SOA ns1.example. hostmaster.example. 1 7200 3600 1209600 300\n\
+             ns1.example. 300 AAAA 2001:db8::53\n\
+             www.example. 300 A 192.0.2.2\n\
+             mail.example. 600 MX 10 mx1.example.\n",
+        );
+        let records = zone::parse_str(
+            "$TTL 300\n\
+             @ SOA ns1 hostmaster 1 7200 3600 1209600 300\n\
+             www A 192.0.2.1\n\
+             mail MX 10 MX1\n",
+            "example.",
+        )
+        .unwrap();
+
+        let findings: Vec<String> = check_zone(&records, &server)
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                "TTL DIFFERS\tmail.example.\t300\tMX\t10 MX1.example. (served TTL 600)",
+                "RDATA DIFFERS\n  zone:   www.example.\t300\tA\t192.0.2.1\
+                 \n  served: www.example.\t300\tA\t192.0.2.2",
+            ]
+        );
+        // ns1 only has an IPv6 address
+        assert_eq!(primary_master(&records, &server).unwrap(), "[2001:db8::53]:53");
+    }
+}
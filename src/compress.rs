@@ -0,0 +1,113 @@
+/* Writing domain names into a message, replacing a name (or its tail) that
+ * has already been written with a pointer to it (RFC 1035 §4.1.4). */
+
+use std::collections::HashMap;
+
+/// Pointers carry a 14 bit offset, so only names starting in the first
+/// 16 KiB of a message can be pointed to.
+const MAX_POINTER_OFFSET: usize = 0x3fff;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    /// Point to earlier names where the format allows it.
+    Enabled,
+    /// Write every name in full.
+    Disabled,
+    /// Write every name in full and in lowercase: the canonical form that
+    /// DNSSEC signatures are computed over (RFC 4034 §6.2).
+    Canonical,
+}
+
+pub struct NameCompressor {
+    compression: Compression,
+    /// Where each name written so far starts, by its labels.
+    offsets: HashMap<Vec<String>, usize>,
+}
+
+impl NameCompressor {
+    pub fn new(compression: Compression) -> NameCompressor {
+        NameCompressor {
+            compression,
+            offsets: HashMap::new(),
+        }
+    }
+
+    /// Append `name` to `wire`, which must hold the message from its start,
+    /// pointing to an earlier copy of its tail if there is one.  Only names
+    /// whose format allows compression may be written this way: owner
+    /// names and the names in the RDATA of the RFC 1035 types.
+    pub fn write_name(&mut self, wire: &mut Vec<u8>, name: &[String]) {
+        if self.compression != Compression::Enabled {
+            self.write_uncompressed(wire, name);
+            return;
+        }
+        let labels: Vec<String> = name.iter().filter(|l| !l.is_empty()).cloned().collect();
+        for index in 0..labels.len() {
+            let tail = &labels[index..];
+            if let Some(&offset) = self.offsets.get(tail) {
+                wire.extend_from_slice(&(0xc000 | offset as u16).to_be_bytes());
+                return;
+            }
+            if wire.len() <= MAX_POINTER_OFFSET {
+                self.offsets.insert(tail.to_vec(), wire.len());
+            }
+            wire.push(labels[index].len() as u8);
+            wire.extend_from_slice(labels[index].as_bytes());
+        }
+        wire.push(0);
+    }
+
+    /// Append `name` to `wire` in full, lowercased in canonical mode.  For
+    /// names that must not be compressed, such as the target of SRV.
+    pub fn write_uncompressed(&mut self, wire: &mut Vec<u8>, name: &[String]) {
+        for label in name.iter().filter(|l| !l.is_empty()) {
+            wire.push(label.len() as u8);
+            if self.compression == Compression::Canonical {
+                wire.extend(label.bytes().map(|b| b.to_ascii_lowercase()));
+            } else {
+                wire.extend_from_slice(label.as_bytes());
+            }
+        }
+        wire.push(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(text: &str) -> Vec<String> {
+        text.split('.').map(String::from).collect()
+    }
+
+    #[test]
+    fn compress_names() {
+        let mut wire = vec![0u8; 12];
+        let mut names = NameCompressor::new(Compression::Enabled);
+        names.write_name(&mut wire, &name("www.Example.com"));
+        names.write_name(&mut wire, &name("mail.Example.com"));
+        names.write_name(&mut wire, &name("www.Example.com"));
+        names.write_name(&mut wire, &name("www.example.com"));
+        assert_eq!(&wire[12..29], b"\x03www\x07Example\x03com\x00");
+        // labels compare exactly, so the lowercase name is written out
+        assert_eq!(
+            &wire[29..],
+            &b"\x04mail\xc0\x10\xc0\x0c\x03www\x07example\xc0\x18"[..]
+        );
+    }
+
+    #[test]
+    fn compress_disabled_and_canonical() {
+        let mut wire = Vec::new();
+        let mut names = NameCompressor::new(Compression::Disabled);
+        names.write_name(&mut wire, &name("A.example"));
+        names.write_name(&mut wire, &name("A.example"));
+        assert_eq!(wire, b"\x01A\x07example\x00\x01A\x07example\x00");
+
+        let mut wire = Vec::new();
+        let mut names = NameCompressor::new(Compression::Canonical);
+        names.write_name(&mut wire, &name("A.EXAMPLE"));
+        names.write_name(&mut wire, &name("a.example"));
+        assert_eq!(wire, b"\x01a\x07example\x00\x01a\x07example\x00");
+    }
+}
@@ -0,0 +1,274 @@
+/* Output in the style of ISC dig, so that lud results can be compared
+ * line-for-line with dig transcripts. */
+
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::message::{Message, RCode, QR};
+use crate::response::Transport;
+use crate::rr::{Class, RR};
+use crate::utils::fqdn;
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Everything dig reports about an exchange besides the message itself.
+pub struct Exchange<'a> {
+    pub server: &'a str,
+    pub transport: Transport,
+    pub query_time: Duration,
+    pub when: SystemTime,
+    pub msg_size: usize,
+}
+
+fn opcode_name(opcode: u8) -> String {
+    match opcode {
+        0 => "QUERY".to_string(),
+        1 => "IQUERY".to_string(),
+        2 => "STATUS".to_string(),
+        4 => "NOTIFY".to_string(),
+        5 => "UPDATE".to_string(),
+        _ => format!("RESERVED{}", opcode),
+    }
+}
+
+fn status_name(message: &Message) -> String {
+    let rcode = message.meta.rcode;
+    if let Some(edns) = &message.edns {
+        if edns.extended_rcode != 0 {
+            let full = (edns.extended_rcode as u16) << 4 | rcode as u16;
+            return match full {
+                16 => "BADVERS".to_string(),
+                _ => format!("RESERVED{}", full),
+            };
+        }
+    }
+    match rcode {
+        RCode::NoError => "NOERROR",
+        RCode::FormatError => "FORMERR",
+        RCode::ServFail => "SERVFAIL",
+        RCode::NameError => "NXDOMAIN",
+        RCode::NotImp => "NOTIMP",
+        RCode::Refused => "REFUSED",
+        RCode::Reserved => "RESERVED",
+    }
+    .to_string()
+}
+
+fn flags(message: &Message) -> String {
+    let meta = &message.meta;
+    let mut flags = Vec::new();
+    if let QR::R = meta.qr {
+        flags.push("qr");
+    }
+    for (set, name) in [
+        (meta.aa, "aa"),
+        (meta.tc, "tc"),
+        (meta.rd, "rd"),
+        (meta.ra, "ra"),
+        (meta.z & 0b010 != 0, "ad"),
+        (meta.z & 0b001 != 0, "cd"),
+    ]
+    .iter()
+    {
+        if *set {
+            flags.push(name);
+        }
+    }
+    flags.join(" ")
+}
+
+/// Pad a field with tabs out to the given column, as dig does for owner names.
+fn pad(field: &str, column: usize) -> String {
+    let mut padded = field.to_string();
+    let mut at = field.len();
+    loop {
+        padded.push('\t');
+        at = (at / 8 + 1) * 8;
+        if at >= column {
+            break;
+        }
+    }
+    padded
+}
+
+fn class_name(class: u16) -> String {
+    match Class::try_from(class) {
+        Ok(class) => format!("{:?}", class),
+        Err(_) => format!("CLASS{}", class),
+    }
+}
+
+fn format_rr(rr: &RR) -> String {
+    format!(
+        "{}{}\t{:?}\t{}\t{}",
+        pad(&fqdn(rr.name()), 24),
+        rr.ttl().as_secs(),
+        rr.class(),
+        rr.rrtype(),
+        rr.rdata().to_presentation()
+    )
+}
+
+fn format_when(when: SystemTime) -> String {
+    let secs = when
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+
+    // civil from days, after Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{} {} {:02} {:02}:{:02}:{:02} UTC {}",
+        DAYS[(days % 7) as usize],
+        MONTHS[(month - 1) as usize],
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        year
+    )
+}
+
+fn format_server(server: &str) -> String {
+    match server.rfind(':') {
+        Some(idx) => {
+            let host = server[..idx].trim_start_matches('[').trim_end_matches(']');
+            format!("{}#{}({})", host, &server[idx + 1..], host)
+        }
+        None => format!("{}#53({})", server, server),
+    }
+}
+
+/// Render a reply the way dig would print it.
+pub fn format(message: &Message, exchange: &Exchange) -> String {
+    let mut out = String::from(";; Got answer:\n");
+    out.push_str(&format!(
+        ";; ->>HEADER<<- opcode: {}, status: {}, id: {}\n",
+        opcode_name(message.meta.opcode),
+        status_name(message),
+        message.id
+    ));
+    out.push_str(&format!(
+        ";; flags: {}; QUERY: {}, ANSWER: {}, AUTHORITY: {}, ADDITIONAL: {}\n",
+        flags(message),
+        message.qdcount,
+        message.ancount,
+        message.nscount,
+        message.arcount
+    ));
+
+    if let Some(edns) = &message.edns {
+        out.push_str("\n;; OPT PSEUDOSECTION:\n");
+        let do_flag = if edns.dnssec_ok { " do" } else { "" };
+        out.push_str(&format!(
+            "; EDNS: version: {}, flags:{}; udp: {}\n",
+            edns.version, do_flag, edns.udp_payload_size
+        ));
+        for option in edns.options.iter() {
+            out.push_str(&format!("; {}\n", option));
+        }
+    }
+
+    if !message.question.is_empty() {
+        out.push_str("\n;; QUESTION SECTION:\n");
+        for q in message.question.iter() {
+            out.push_str(&format!(
+                "{}{}\t{}\n",
+                pad(&format!(";{}", fqdn(&q.qname)), 32),
+                class_name(q.qclass),
+                q.qtype
+            ));
+        }
+    }
+
+    for (name, section) in [
+        ("ANSWER", &message.answer),
+        ("AUTHORITY", &message.authority),
+        ("ADDITIONAL", &message.additional),
+    ]
+    .iter()
+    {
+        if !section.is_empty() {
+            out.push_str(&format!("\n;; {} SECTION:\n", name));
+            for rr in section.iter() {
+                out.push_str(&format_rr(rr));
+                out.push('\n');
+            }
+        }
+    }
+
+    out.push_str(&format!(
+        "\n;; Query time: {} msec\n",
+        exchange.query_time.as_millis()
+    ));
+    out.push_str(&format!(
+        ";; SERVER: {} ({})\n",
+        format_server(exchange.server),
+        exchange.transport
+    ));
+    out.push_str(&format!(";; WHEN: {}\n", format_when(exchange.when)));
+    out.push_str(&format!(";; MSG SIZE  rcvd: {}\n", exchange.msg_size));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dig_when() {
+        let when = UNIX_EPOCH + Duration::from_secs(1_582_502_400); // 2020-02-24
+        assert_eq!(format_when(when), "Mon Feb 24 00:00:00 UTC 2020");
+    }
+
+    #[test]
+    fn dig_server() {
+        assert_eq!(format_server("1.1.1.1:53"), "1.1.1.1#53(1.1.1.1)");
+        assert_eq!(format_server("[::1]:53"), "::1#53(::1)");
+    }
+
+    #[test]
+    fn dig_pad() {
+        assert_eq!(pad("example.com.", 24), "example.com.\t\t");
+        assert_eq!(pad(";example.com.", 32), ";example.com.\t\t\t");
+    }
+
+    #[test]
+    fn dig_format_reply() {
+        let msg: [u8; 45] = [
+            0x3e, 0x1d, 0x81, 0x80, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x07, 0x65,
+            0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x03, 0x63, 0x6f, 0x6d, 0x00, 0x00, 0x01, 0x00,
+            0x01, 0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x1c, 0x3e, 0x00, 0x04, 0x5d,
+            0xb8, 0xd8, 0x22,
+        ];
+        let message = Message::from_wire(&msg[..]).unwrap();
+        let exchange = Exchange {
+            server: "192.0.2.53:53",
+            transport: Transport::Tcp,
+            query_time: Duration::from_millis(12),
+            when: UNIX_EPOCH + Duration::from_secs(1_582_502_400),
+            msg_size: msg.len(),
+        };
+        let out = format(&message, &exchange);
+        assert!(out.contains(";; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 15901\n"));
+        assert!(out.contains(";; flags: qr rd ra; QUERY: 1, ANSWER: 1, AUTHORITY: 0"));
+        assert!(out.contains(";example.com.\t\t\tIN\tA\n"));
+        assert!(out.contains("example.com.\t\t7230\tIN\tA\t93.184.216.34\n"));
+        assert!(out.contains(";; SERVER: 192.0.2.53#53(192.0.2.53) (TCP)\n"));
+        assert!(out.contains(";; MSG SIZE  rcvd: 45\n"));
+    }
+}
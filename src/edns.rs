@@ -0,0 +1,173 @@
+use crate::errors::ParseError;
+use crate::utils::{byte_combine, TRUNCATED};
+use std::error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EdnsOption {
+    pub code: u16,
+    pub data: Vec<u8>,
+}
+
+impl EdnsOption {
+    fn name(&self) -> String {
+        match self.code {
+            3 => "NSID".to_string(),
+            8 => "CLIENT-SUBNET".to_string(),
+            10 => "COOKIE".to_string(),
+            11 => "TCP-KEEPALIVE".to_string(),
+            12 => "PADDING".to_string(),
+            15 => "EDE".to_string(),
+            code => format!("OPT={}", code),
+        }
+    }
+}
+
+impl fmt::Display for EdnsOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut hex = String::new();
+        for byte in self.data.iter() {
+            hex.push_str(&format!("{:02x}", byte));
+        }
+        write!(f, "{}: {}", self.name(), hex)
+    }
+}
+
+/* The OPT pseudo-RR (RFC 6891), which overloads the CLASS and TTL fields of
+ * an ordinary RR to carry the EDNS parameters of the sender.
+ *
+ *  CLASS: requestor's UDP payload size
+ *  TTL:   | EXTENDED-RCODE | VERSION | DO |  Z  |
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Edns {
+    pub udp_payload_size: u16,
+    pub extended_rcode: u8,
+    pub version: u8,
+    pub dnssec_ok: bool,
+    pub z: u16,
+    pub options: Vec<EdnsOption>,
+}
+
+/// The UDP payload size we advertise, small enough to avoid IP
+/// fragmentation on common paths.
+pub const DEFAULT_UDP_PAYLOAD: u16 = 1232;
+
+impl Edns {
+    pub fn new(udp_payload_size: u16) -> Edns {
+        Edns {
+            udp_payload_size,
+            extended_rcode: 0,
+            version: 0,
+            dnssec_ok: false,
+            z: 0,
+            options: Vec::new(),
+        }
+    }
+
+    /// The whole OPT record, owner name included.
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = vec![0u8]; // root
+        wire.extend_from_slice(&41u16.to_be_bytes());
+        wire.extend_from_slice(&self.udp_payload_size.to_be_bytes());
+        wire.push(self.extended_rcode);
+        wire.push(self.version);
+        let flags = (self.dnssec_ok as u16) << 15 | self.z & 0x7fff;
+        wire.extend_from_slice(&flags.to_be_bytes());
+
+        let mut rdata = Vec::new();
+        for option in self.options.iter() {
+            rdata.extend_from_slice(&option.code.to_be_bytes());
+            rdata.extend_from_slice(&(option.data.len() as u16).to_be_bytes());
+            rdata.extend_from_slice(&option.data);
+        }
+        wire.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        wire.extend_from_slice(&rdata);
+        wire
+    }
+
+    /// Parse an OPT record whose TYPE field starts at `offset` (the owner
+    /// name of an OPT record is always the root, a single zero octet).
+    pub fn from_wire(
+        buf: &[u8],
+        mut offset: usize,
+    ) -> Result<(Edns, usize), &'static dyn error::Error> {
+        // TYPE, CLASS, TTL and RDLENGTH
+        if buf.len() < offset + 10 {
+            return Err(TRUNCATED);
+        }
+        offset += 2; // TYPE, already known to be OPT
+        let udp_payload_size = byte_combine(buf[offset], buf[offset + 1]);
+        offset += 2;
+        let extended_rcode = buf[offset];
+        let version = buf[offset + 1];
+        let flags = byte_combine(buf[offset + 2], buf[offset + 3]);
+        offset += 4;
+        let rdlength = byte_combine(buf[offset], buf[offset + 1]) as usize;
+        offset += 2;
+
+        let end = offset + rdlength;
+        let rdata = buf.get(..end).ok_or(TRUNCATED)?;
+        let mut options = Vec::new();
+        while offset < end {
+            if offset + 4 > end {
+                return Err(&ParseError::InvalidRData);
+            }
+            let code = byte_combine(rdata[offset], rdata[offset + 1]);
+            let length = byte_combine(rdata[offset + 2], rdata[offset + 3]) as usize;
+            offset += 4;
+            let data = rdata
+                .get(offset..offset + length)
+                .ok_or(&ParseError::InvalidRData as &dyn error::Error)?;
+            options.push(EdnsOption {
+                code,
+                data: data.to_owned(),
+            });
+            offset += length;
+        }
+
+        let edns = Edns {
+            udp_payload_size,
+            extended_rcode,
+            version,
+            dnssec_ok: (flags >> 15) != 0,
+            z: flags & 0x7fff,
+            options,
+        };
+        Ok((edns, end))
+    }
+}
+
+impl fmt::Display for Edns {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut to_write = format!(
+            "VERSION: {}; UDP: {}; DO: {:?}; EXTENDED RCODE: {}",
+            self.version, self.udp_payload_size, self.dnssec_ok, self.extended_rcode
+        );
+        for option in self.options.iter() {
+            to_write.push_str(&format!("\n{}", option));
+        }
+        write!(f, "{}", to_write)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edns_from_wire() {
+        // TYPE OPT, UDP 1232, ext rcode 0, version 0, DO set, one COOKIE option
+        let wire = [
+            0x00, 0x29, 0x04, 0xd0, 0x00, 0x00, 0x80, 0x00, 0x00, 0x0c, 0x00, 0x0a, 0x00, 0x08,
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+        ];
+        let (edns, offset) = Edns::from_wire(&wire, 0).unwrap();
+        assert_eq!(offset, wire.len());
+        assert_eq!(edns.udp_payload_size, 1232);
+        assert!(edns.dnssec_ok);
+        assert_eq!(edns.options.len(), 1);
+        assert_eq!(edns.options[0].to_string(), "COOKIE: 0102030405060708");
+        assert_eq!(edns.to_wire()[1..], wire[..]);
+    }
+}
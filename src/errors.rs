@@ -6,6 +6,13 @@ pub enum ParseError {
     InvalidClass,
     InvalidRRType,
     PointerForward,
+    PointerLoop,
+    NameTooLong,
+    InvalidLabelType,
+    Truncated,
+    InvalidName,
+    InvalidEscape,
+    InvalidRData,
 }
 
 impl error::Error for ParseError {
@@ -14,6 +21,13 @@ impl error::Error for ParseError {
             ParseError::InvalidClass => "InvalidClass",
             ParseError::InvalidRRType => "InvalidRRType",
             ParseError::PointerForward => "PointerForward",
+            ParseError::PointerLoop => "PointerLoop",
+            ParseError::NameTooLong => "NameTooLong",
+            ParseError::InvalidLabelType => "InvalidLabelType",
+            ParseError::Truncated => "Truncated",
+            ParseError::InvalidName => "InvalidName",
+            ParseError::InvalidEscape => "InvalidEscape",
+            ParseError::InvalidRData => "InvalidRData",
         }
     }
 
@@ -1,17 +1,43 @@
 use std::net::UdpSocket;
-
+use std::time::{Duration, Instant, SystemTime};
+
+pub mod batch;
+pub mod bench;
+pub mod cache;
+pub mod check;
+pub mod compress;
+pub mod dig;
+mod edns;
 mod errors;
+pub mod mailauth;
 pub mod message;
-mod question;
-mod rdata;
+pub mod pcap;
+pub mod question;
+pub mod rdata;
+pub mod recording;
 pub mod resconf;
-mod rr;
+pub mod resolver;
+pub mod response;
+pub mod reverse;
+pub mod rr;
+pub mod rrset;
+pub mod spf;
+pub mod transport;
+pub mod ttl;
 mod utils;
+pub mod zone;
 
 use message::Message;
+use response::{Response, Transport};
 use std::io;
 
-fn parse_name(mut name: String) -> Vec<String> {
+pub use utils::extract_name;
+
+pub const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Split a name in presentation form into labels, converting Unicode
+/// labels to punycode.
+pub fn parse_name(mut name: String) -> Vec<String> {
     // XXX: name really needs to be bytes
     if !name.ends_with(".") {
         name.push('.');
@@ -38,22 +64,63 @@ fn parse_name(mut name: String) -> Vec<String> {
     labels
 }
 
+/// A standard query for `name` (in presentation form) with RD set; use the
+/// `with_*` methods of `Message` to change its flags.
+pub fn new_query(name: String, qtype: String) -> Message {
+    Message::new(parse_name(name), qtype)
+}
+
 pub fn send_query(
-    mut recv_buf: &mut [u8],
+    recv_buf: &mut [u8],
     name: String,
     qtype: String,
     resolver: String,
 ) -> io::Result<usize> {
-    let labels = parse_name(name);
-    let q_message = Message::new(labels, qtype);
+    send_message(recv_buf, &new_query(name, qtype), resolver)
+}
+
+/// Send a prepared query and receive the reply into `recv_buf`.
+pub fn send_message(
+    mut recv_buf: &mut [u8],
+    q_message: &Message,
+    resolver: String,
+) -> io::Result<usize> {
     let buf = q_message.to_wire();
 
     let sock = UdpSocket::bind("0.0.0.0:0").expect("Couldn't bind to this address");
+    sock.set_read_timeout(Some(QUERY_TIMEOUT))?;
 
     sock.send_to(&buf, resolver).expect("Failed to send");
     sock.recv(&mut recv_buf)
 }
 
+/// Send a query and return the reply along with where it came from, how
+/// long it took and its size.
+pub fn query(name: String, qtype: String, resolver: String) -> io::Result<Response> {
+    query_message(&new_query(name, qtype), resolver)
+}
+
+/// Send a prepared query and return the reply as `query` does.
+pub fn query_message(q_message: &Message, resolver: String) -> io::Result<Response> {
+    let mut recv_buf = [0u8; message::DNS_MSG_MAX];
+    let when = SystemTime::now();
+    let started = Instant::now();
+    let received = send_message(&mut recv_buf, q_message, resolver.clone())?;
+    let elapsed = started.elapsed();
+    Response::new(
+        &recv_buf[..received],
+        resolver,
+        Transport::Udp,
+        when,
+        elapsed,
+    )
+}
+
+/// Send a query and parse the reply.
+pub fn lookup(name: String, qtype: String, resolver: String) -> io::Result<Message> {
+    query(name, qtype, resolver).map(|response| response.message)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
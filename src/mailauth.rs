@@ -0,0 +1,391 @@
+/* The TXT records that publish a domain's email authentication policy:
+ * DMARC (RFC 7489), DKIM keys (RFC 6376), MTA-STS (RFC 8461), SMTP TLS
+ * reporting (RFC 8460) and BIMI.  All of them are tag=value lists. */
+
+use std::fmt;
+
+use crate::message::RCode;
+use crate::rdata::RData;
+use crate::resolver::Resolver;
+use crate::rr::RRType;
+use crate::utils::base64_decode;
+
+pub struct Check {
+    pub kind: &'static str,
+    /// The owner name that was queried.
+    pub name: String,
+    pub record: Option<String>,
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.record {
+            Some(record) => write!(f, "{}\t{}: {}", self.kind, self.name, record)?,
+            None => write!(f, "{}\t{}: no record", self.kind, self.name)?,
+        }
+        for problem in self.problems.iter() {
+            write!(f, "\n  {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+type Tags = Vec<(String, String)>;
+
+/// Split a tag-list (RFC 6376 §3.2) into its tags, in order.
+fn parse_tags(text: &str) -> Result<Tags, String> {
+    let mut tags: Tags = Vec::new();
+    for spec in text.split(';') {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            continue;
+        }
+        let (name, value) = match spec.find('=') {
+            Some(idx) => (spec[..idx].trim(), spec[idx + 1..].trim()),
+            None => return Err(format!("\"{}\" is not of the form tag=value", spec)),
+        };
+        let valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name {
+            return Err(format!("\"{}\" is not a valid tag name", name));
+        }
+        if tags.iter().any(|(seen, _)| seen == name) {
+            return Err(format!("tag {} appears more than once", name));
+        }
+        tags.push((name.to_string(), value.to_string()));
+    }
+    Ok(tags)
+}
+
+fn tag<'a>(tags: &'a Tags, name: &str) -> Option<&'a str> {
+    tags.iter()
+        .find(|(tag, _)| tag == name)
+        .map(|(_, value)| value.as_str())
+}
+
+fn check_version(tags: &Tags, version: &str, problems: &mut Vec<String>) {
+    match tags.first() {
+        Some((name, value)) if name == "v" && value == version => (),
+        _ => problems.push(format!("v={} must be the first tag", version)),
+    }
+}
+
+fn check_one_of(tags: &Tags, name: &str, allowed: &[&str], problems: &mut Vec<String>) {
+    if let Some(value) = tag(tags, name) {
+        if !allowed.contains(&value) {
+            problems.push(format!(
+                "{}={} is not one of {}",
+                name,
+                value,
+                allowed.join(", ")
+            ));
+        }
+    }
+}
+
+/// Check a comma-separated list of URIs, each with one of `schemes`.
+fn check_uris(tags: &Tags, name: &str, schemes: &[&str], problems: &mut Vec<String>) {
+    if let Some(value) = tag(tags, name) {
+        for uri in value.split(',').map(str::trim) {
+            let scheme = uri.find(':').map(|idx| uri[..idx].to_lowercase());
+            let rest = uri.find(':').map(|idx| &uri[idx + 1..]).unwrap_or("");
+            match scheme {
+                Some(scheme) if schemes.contains(&scheme.as_str()) && !rest.is_empty() => (),
+                _ => problems.push(format!(
+                    "{}: \"{}\" is not a {} URI",
+                    name,
+                    uri,
+                    schemes.join(" or ")
+                )),
+            }
+        }
+    }
+}
+
+fn check_dmarc(tags: &Tags, problems: &mut Vec<String>) {
+    check_version(tags, "DMARC1", problems);
+    match tag(tags, "p") {
+        Some(_) => check_one_of(tags, "p", &["none", "quarantine", "reject"], problems),
+        None => problems.push("the policy tag p is required".to_string()),
+    }
+    check_one_of(tags, "sp", &["none", "quarantine", "reject"], problems);
+    check_one_of(tags, "adkim", &["r", "s"], problems);
+    check_one_of(tags, "aspf", &["r", "s"], problems);
+    check_one_of(tags, "rf", &["afrf"], problems);
+    if let Some(pct) = tag(tags, "pct") {
+        if !matches!(pct.parse::<u8>(), Ok(0..=100)) {
+            problems.push(format!("pct={} is not a percentage", pct));
+        }
+    }
+    if let Some(ri) = tag(tags, "ri") {
+        if ri.parse::<u32>().is_err() {
+            problems.push(format!("ri={} is not a number of seconds", ri));
+        }
+    }
+    if let Some(fo) = tag(tags, "fo") {
+        if !fo
+            .split(':')
+            .all(|o| ["0", "1", "d", "s"].contains(&o.trim()))
+        {
+            problems.push(format!("fo={} is not a list of 0, 1, d and s", fo));
+        }
+    }
+    // a size limit may follow a report URI, e.g. mailto:d@example.com!10m
+    let strip_limits = |tags: &Tags| -> Tags {
+        tags.iter()
+            .map(|(name, value)| {
+                let value = value
+                    .split(',')
+                    .map(|uri| uri.trim().split('!').next().unwrap_or(""))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                (name.clone(), value)
+            })
+            .collect()
+    };
+    let stripped = strip_limits(tags);
+    check_uris(&stripped, "rua", &["mailto"], problems);
+    check_uris(&stripped, "ruf", &["mailto"], problems);
+}
+
+fn check_dkim(tags: &Tags, problems: &mut Vec<String>) {
+    if tags.iter().position(|(name, _)| name == "v").unwrap_or(0) != 0 {
+        problems.push("v=DKIM1 must be the first tag".to_string());
+    }
+    check_one_of(tags, "v", &["DKIM1"], problems);
+    check_one_of(tags, "k", &["rsa", "ed25519"], problems);
+    if let Some(h) = tag(tags, "h") {
+        if !h
+            .split(':')
+            .all(|alg| ["sha1", "sha256"].contains(&alg.trim()))
+        {
+            problems.push(format!("h={} names an unknown hash algorithm", h));
+        }
+    }
+    if let Some(t) = tag(tags, "t") {
+        if !t.split(':').all(|flag| ["y", "s"].contains(&flag.trim())) {
+            problems.push(format!("t={} has unknown flags", t));
+        }
+    }
+    match tag(tags, "p") {
+        None => problems.push("the public key tag p is required".to_string()),
+        Some("") => problems.push("the key has been revoked (empty p=)".to_string()),
+        Some(key) => {
+            let key: String = key.chars().filter(|c| !c.is_whitespace()).collect();
+            if base64_decode(&key).is_err() {
+                problems.push("p= is not valid base64".to_string());
+            }
+        }
+    }
+}
+
+fn check_mta_sts(tags: &Tags, problems: &mut Vec<String>) {
+    check_version(tags, "STSv1", problems);
+    match tag(tags, "id") {
+        Some(id)
+            if !id.is_empty()
+                && id.len() <= 32
+                && id.chars().all(|c| c.is_ascii_alphanumeric()) => {}
+        Some(id) => problems.push(format!("id={} must be 1 to 32 letters and digits", id)),
+        None => problems.push("the policy tag id is required".to_string()),
+    }
+}
+
+fn check_tls_rpt(tags: &Tags, problems: &mut Vec<String>) {
+    check_version(tags, "TLSRPTv1", problems);
+    match tag(tags, "rua") {
+        Some(_) => check_uris(tags, "rua", &["mailto", "https"], problems),
+        None => problems.push("the report tag rua is required".to_string()),
+    }
+}
+
+fn check_bimi(tags: &Tags, problems: &mut Vec<String>) {
+    check_version(tags, "BIMI1", problems);
+    for name in ["l", "a"].iter() {
+        if tag(tags, name) != Some("") {
+            check_uris(tags, name, &["https"], problems);
+        }
+    }
+    if tag(tags, "l").is_none() {
+        problems.push("the logo tag l is required".to_string());
+    }
+}
+
+/// Look up one TXT-published policy and validate it.  `prefix` selects
+/// the records that are meant to be policies, `required` says whether the
+/// absence of a record is a problem.
+fn check<R: Resolver>(
+    resolver: &R,
+    kind: &'static str,
+    name: String,
+    prefix: &str,
+    required: bool,
+    validate: fn(&Tags, &mut Vec<String>),
+) -> Check {
+    let mut check = Check {
+        kind,
+        name,
+        record: None,
+        problems: Vec::new(),
+    };
+
+    let reply = match resolver.resolve(&check.name, RRType::TXT) {
+        Ok(reply) => reply,
+        Err(e) => {
+            check.problems.push(format!("lookup failed: {}", e));
+            return check;
+        }
+    };
+    match reply.meta.rcode {
+        RCode::NoError | RCode::NameError => (),
+        rcode => {
+            check.problems.push(format!("lookup failed: {:?}", rcode));
+            return check;
+        }
+    }
+
+    let mut records: Vec<String> = reply
+        .answer
+        .iter()
+        .filter_map(|rr| match rr.rdata() {
+            RData::TXT(data) => Some(data.joined_text()),
+            _ => None,
+        })
+        .filter(|text| text.starts_with(prefix))
+        .collect();
+    match records.len() {
+        0 if required => check.problems.push(format!("no {} record", kind)),
+        0 => (),
+        1 => (),
+        n => check
+            .problems
+            .push(format!("{} records found, expected one", n)),
+    }
+    if records.is_empty() {
+        return check;
+    }
+
+    let record = records.remove(0);
+    match parse_tags(&record) {
+        Ok(tags) => validate(&tags, &mut check.problems),
+        Err(e) => check.problems.push(e),
+    }
+    check.record = Some(record);
+    check
+}
+
+/// Look up and validate every email authentication record of `domain`,
+/// including the DKIM keys of the given selectors.
+pub fn report<R: Resolver>(resolver: &R, domain: &str, selectors: &[String]) -> Vec<Check> {
+    let domain = domain.trim_end_matches('.');
+    let mut checks = vec![check(
+        resolver,
+        "DMARC",
+        format!("_dmarc.{}", domain),
+        "v=DMARC1",
+        true,
+        check_dmarc,
+    )];
+    for selector in selectors.iter() {
+        checks.push(check(
+            resolver,
+            "DKIM",
+            format!("{}._domainkey.{}", selector, domain),
+            "",
+            true,
+            check_dkim,
+        ));
+    }
+    checks.push(check(
+        resolver,
+        "MTA-STS",
+        format!("_mta-sts.{}", domain),
+        "v=STSv1",
+        false,
+        check_mta_sts,
+    ));
+    checks.push(check(
+        resolver,
+        "TLSRPT",
+        format!("_smtp._tls.{}", domain),
+        "v=TLSRPTv1",
+        false,
+        check_tls_rpt,
+    ));
+    checks.push(check(
+        resolver,
+        "BIMI",
+        format!("default._bimi.{}", domain),
+        "v=BIMI1",
+        false,
+        check_bimi,
+    ));
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::ZoneResolver;
+
+    fn problems(domain: &str, selectors: &[&str], zone: &'static str) -> Vec<Vec<String>> {
+        let resolver = ZoneResolver { zone };
+        let selectors: Vec<String> = selectors.iter().map(|s| s.to_string()).collect();
+        report(&resolver, domain, &selectors)
+            .into_iter()
+            .map(|check| check.problems)
+            .collect()
+    }
+
+    #[test]
+    fn mailauth_tags() {
+        let tags = parse_tags("v=DMARC1; p=reject;  rua = mailto:d@example.com;").unwrap();
+        assert_eq!(tag(&tags, "rua"), Some("mailto:d@example.com"));
+        assert!(parse_tags("v=DMARC1; p").is_err());
+        assert!(parse_tags("v=DMARC1; p=none; p=reject").is_err());
+        assert!(parse_tags("1v=DMARC1").is_err());
+    }
+
+    #[test]
+    fn mailauth_valid() {
+        let zone = "$ORIGIN example.\n\
+            $TTL 300\n\
+            _dmarc TXT \"v=DMARC1; p=quarantine; pct=50; rua=mailto:d@example.com!10m\"\n\
+            s1._domainkey TXT \"v=DKIM1; k=rsa; \" \"p=MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQ==\"\n\
+            _mta-sts TXT \"v=STSv1; id=20190429T010101\"\n\
+            _smtp._tls TXT \"v=TLSRPTv1; rua=https://reports.example/tls\"\n\
+            default._bimi TXT \"v=BIMI1; l=https://example/logo.svg; a=\"\n\
+            default._bimi TXT \"unrelated\"\n";
+        let empty: Vec<String> = Vec::new();
+        assert_eq!(problems("example", &["s1"], zone), vec![empty; 5]);
+    }
+
+    #[test]
+    fn mailauth_problems() {
+        let zone = "$ORIGIN example.\n\
+            $TTL 300\n\
+            _dmarc TXT \"v=DMARC1; p=block; pct=150; rua=d@example.com\"\n\
+            s1._domainkey TXT \"k=dsa; p=\"\n\
+            _mta-sts TXT \"v=STSv1; id=not-valid\"\n\
+            _smtp._tls TXT \"v=TLSRPTv1\"\n";
+        assert_eq!(
+            problems("example", &["s1", "s2"], zone),
+            vec![
+                vec![
+                    "p=block is not one of none, quarantine, reject",
+                    "pct=150 is not a percentage",
+                    "rua: \"d@example.com\" is not a mailto URI",
+                ],
+                vec![
+                    "k=dsa is not one of rsa, ed25519",
+                    "the key has been revoked (empty p=)",
+                ],
+                vec!["no DKIM record"],
+                vec!["id=not-valid must be 1 to 32 letters and digits"],
+                vec!["the report tag rua is required"],
+                vec![],
+            ]
+        );
+    }
+}
@@ -1,20 +1,116 @@
-use clap::{App, Arg, ArgMatches};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 
-use lud::{message, resconf, send_query};
+use lud::message::Message;
+use lud::pcap::PcapRecorder;
+use lud::question::QType;
+use lud::recording::{record_all, Record, Recorder, Replay};
+use lud::resolver::{Resolver, UdpResolver};
+use lud::response::{json_string, Response};
+use lud::rr::Class;
+use lud::transport::Engine;
+use lud::{
+    batch, bench, check, dig, mailauth, message, new_query, parse_name, pcap, query_message,
+    resconf, reverse, send_message, spf, zone,
+};
 
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{self, Read};
+use std::net::IpAddr;
 use std::process;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
 
 fn parse_cli<'a>() -> ArgMatches<'a> {
     App::new("lud")
         .version(clap::crate_version!())
         .about("DNS Lookup Client")
         .author("Joshua Crowgey")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name("name")
-                .help("what to look up")
-                .required(true)
+                .help("what to look up; several names are sent as one query with several questions")
+                .required_unless_one(&["reverse", "file"])
+                .takes_value(true)
+                .multiple(true),
+        )
+        .arg(
+            Arg::with_name("reverse")
+                .short("x")
+                .long("reverse")
+                .help("look up the PTR records of an address, or the name of a network")
+                .value_name("address[/prefix]")
+                .required(false)
+                .takes_value(true)
+                .conflicts_with("name"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Print each reply and how it was obtained as JSON")
+                .required(false)
+                .takes_value(false)
+                .conflicts_with_all(&["dig", "raw"]),
+        )
+        .arg(
+            Arg::with_name("no-rd")
+                .long("no-rd")
+                .help("Clear Recursion Desired, to see what a server has cached or is authoritative for")
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("ad")
+                .long("ad")
+                .help("Set Authentic Data, asking whether the answer was validated")
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("cd")
+                .long("cd")
+                .help("Set Checking Disabled, asking for answers without DNSSEC validation")
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("do")
+                .long("do")
+                .help("Set DNSSEC OK (in an EDNS OPT record), asking for DNSSEC records")
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("opcode")
+                .long("opcode")
+                .help("the opcode, by name (QUERY, IQUERY, STATUS, NOTIFY, UPDATE) or number")
+                .required(false)
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("file")
+                .short("f")
+                .long("file")
+                .help("look up each `name [type] [@server]` line of a file (- for stdin)")
+                .required(false)
+                .takes_value(true)
+                .conflicts_with_all(&["name", "reverse"]),
+        )
+        .arg(
+            Arg::with_name("in-flight")
+                .long("in-flight")
+                .help("how many queries of a batch to have outstanding at once")
+                .required(false)
+                .takes_value(true)
+                .default_value("10"),
+        )
+        .arg(
+            Arg::with_name("unordered")
+                .long("unordered")
+                .help("print batch results as they complete, not in input order")
+                .required(false)
+                .takes_value(false),
+        )
         .arg(
             Arg::with_name("qtype")
                 .short("q")
@@ -23,6 +119,14 @@ fn parse_cli<'a>() -> ArgMatches<'a> {
                 .required(false)
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("class")
+                .short("c")
+                .long("class")
+                .help("the class to ask in: IN (default), CH, HS, NONE or ANY")
+                .required(false)
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("server")
                 .short("s")
@@ -37,36 +141,661 @@ fn parse_cli<'a>() -> ArgMatches<'a> {
                 .long("raw")
                 .help("Print the raw reply, no parsing")
                 .required(false)
+                .takes_value(false)
+                .conflicts_with_all(&["record", "replay", "pcap"]),
+        )
+        .arg(
+            Arg::with_name("dig")
+                .long("dig")
+                .help("Print the reply in the style of dig")
+                .required(false)
                 .takes_value(false),
         )
+        .args(&recording_args())
+        .subcommand(
+            SubCommand::with_name("bench")
+                .about("Replay a query list against a server and report its performance")
+                .arg(
+                    Arg::with_name("queryfile")
+                        .help("queries, one `name [type] [@server]` per line (- for stdin)")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("server")
+                        .short("s")
+                        .long("server")
+                        .help("which DNS server to load")
+                        .required(false)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("qps")
+                        .long("qps")
+                        .help("queries to send per second")
+                        .required(false)
+                        .takes_value(true)
+                        .default_value("100"),
+                )
+                .arg(
+                    Arg::with_name("count")
+                        .short("n")
+                        .long("count")
+                        .help("queries to send in all (default: each query once)")
+                        .required(false)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("in-flight")
+                        .long("in-flight")
+                        .help("most queries to have outstanding at once")
+                        .required(false)
+                        .takes_value(true)
+                        .default_value("100"),
+                )
+                .arg(
+                    Arg::with_name("timeout")
+                        .long("timeout")
+                        .help("milliseconds to wait for each reply")
+                        .required(false)
+                        .takes_value(true)
+                        .default_value("2000"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("check-zone")
+                .about("Compare a zone file with what its authoritative server serves")
+                .arg(
+                    Arg::with_name("zonefile")
+                        .help("the master file to check")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("origin")
+                        .short("o")
+                        .long("origin")
+                        .help("origin for relative names in the zone file")
+                        .required(false)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("server")
+                        .short("s")
+                        .long("server")
+                        .help("authoritative server to check (default: the SOA MNAME)")
+                        .required(false)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("mail-auth")
+                .about("Check a domain's DMARC, DKIM, MTA-STS, TLS-RPT and BIMI records")
+                .arg(
+                    Arg::with_name("domain")
+                        .help("the mail domain to check")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("selector")
+                        .short("d")
+                        .long("dkim")
+                        .help("a DKIM selector whose key to check (repeatable)")
+                        .required(false)
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("server")
+                        .short("s")
+                        .long("server")
+                        .help("which DNS server to use")
+                        .required(false)
+                        .takes_value(true),
+                )
+                .args(&recording_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("pcap")
+                .about("Decode the DNS messages in a pcap or pcapng file")
+                .arg(
+                    Arg::with_name("capture")
+                        .help("the capture file, - for stdin")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("spf")
+                .about("Evaluate a domain's SPF policy for a sender IP")
+                .arg(
+                    Arg::with_name("domain")
+                        .help("the domain whose policy to evaluate")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("ip")
+                        .help("the IP address mail is sent from")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("sender")
+                        .long("sender")
+                        .help("the envelope sender (default: postmaster@<domain>)")
+                        .required(false)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("server")
+                        .short("s")
+                        .long("server")
+                        .help("which DNS server to use")
+                        .required(false)
+                        .takes_value(true),
+                )
+                .args(&recording_args()),
+        )
         .get_matches()
 }
 
+fn recording_args<'a, 'b>() -> [Arg<'a, 'b>; 3] {
+    [
+        Arg::with_name("record")
+            .long("record")
+            .help("Append every query and its reply to a file")
+            .value_name("file")
+            .required(false)
+            .takes_value(true)
+            .conflicts_with("replay"),
+        Arg::with_name("replay")
+            .long("replay")
+            .help("Answer queries from a file written by --record instead of the network")
+            .value_name("file")
+            .required(false)
+            .takes_value(true),
+        Arg::with_name("pcap")
+            .long("pcap")
+            .help("Write every query and its reply to a pcap file")
+            .value_name("file")
+            .required(false)
+            .takes_value(true)
+            .conflicts_with("replay"),
+    ]
+}
+
+/// Whether lookups are recorded, or answered from a recording.
+struct Recording {
+    recorders: Vec<Arc<dyn Record>>,
+    replay: Option<Replay>,
+}
+
+impl Recording {
+    fn from_cli(cli: &ArgMatches) -> Result<Recording, String> {
+        let mut recorders: Vec<Arc<dyn Record>> = Vec::new();
+        if let Some(path) = cli.value_of("record") {
+            let recorder = Recorder::create(path).map_err(|e| format!("{}: {}", path, e))?;
+            recorders.push(Arc::new(recorder));
+        }
+        if let Some(path) = cli.value_of("pcap") {
+            let recorder = PcapRecorder::create(path).map_err(|e| format!("{}: {}", path, e))?;
+            recorders.push(Arc::new(recorder));
+        }
+        let replay = match cli.value_of("replay") {
+            Some(path) => Some(Replay::open(path).map_err(|e| format!("{}: {}", path, e))?),
+            None => None,
+        };
+        Ok(Recording { recorders, replay })
+    }
+
+    /// Answer `query` from the recording when replaying; otherwise `send`
+    /// it, recording the reply if asked to.  A reply that can't be recorded
+    /// is still returned.
+    fn lookup<S>(&self, query: Message, server: &str, send: S) -> io::Result<Response>
+    where
+        S: FnOnce(Message) -> io::Result<Response>,
+    {
+        if let Some(replay) = &self.replay {
+            return replay.lookup_message(&query, server);
+        }
+        let response = send(query.clone())?;
+        record_all(&self.recorders, &query, &response);
+        Ok(response)
+    }
+
+    fn resolver(self, server: String) -> Box<dyn Resolver> {
+        if let Some(replay) = self.replay {
+            return Box::new(replay);
+        }
+        let mut resolver = UdpResolver::new(server);
+        for recorder in self.recorders {
+            resolver = resolver.with_recorder(recorder);
+        }
+        Box::new(resolver)
+    }
+}
+
+/// The header flags and opcode asked for on the command line.
+#[derive(Clone, Copy)]
+struct QueryFlags {
+    rd: bool,
+    ad: bool,
+    cd: bool,
+    dnssec_ok: bool,
+    opcode: u8,
+    class: Class,
+}
+
+impl QueryFlags {
+    fn from_cli(cli: &ArgMatches) -> Result<QueryFlags, String> {
+        let opcode = match cli.value_of("opcode").map(str::to_uppercase) {
+            None => 0,
+            Some(name) => match name.as_ref() {
+                "QUERY" => 0,
+                "IQUERY" => 1,
+                "STATUS" => 2,
+                "NOTIFY" => 4,
+                "UPDATE" => 5,
+                number => match number.parse::<u8>() {
+                    Ok(opcode) if opcode < 16 => opcode,
+                    _ => return Err(format!("unknown opcode {}", name)),
+                },
+            },
+        };
+        let class = match cli.value_of("class") {
+            None => Class::IN,
+            Some(name) => {
+                Class::try_from(name.to_string()).map_err(|_| format!("unknown class {}", name))?
+            }
+        };
+        Ok(QueryFlags {
+            class,
+            rd: !cli.is_present("no-rd"),
+            ad: cli.is_present("ad"),
+            cd: cli.is_present("cd"),
+            dnssec_ok: cli.is_present("do"),
+            opcode,
+        })
+    }
+
+    fn query(&self, name: String, qtype: String) -> Message {
+        new_query(name, qtype)
+            .with_class(self.class)
+            .with_rd(self.rd)
+            .with_ad(self.ad)
+            .with_cd(self.cd)
+            .with_opcode(self.opcode)
+            .with_dnssec_ok(self.dnssec_ok)
+    }
+}
+
+fn read_input(path: &str) -> io::Result<String> {
+    let mut text = String::new();
+    if path == "-" {
+        io::stdin().read_to_string(&mut text)?;
+    } else {
+        text = fs::read_to_string(path)?;
+    }
+    Ok(text)
+}
+
+fn run_bench(cli: &ArgMatches) -> i32 {
+    let path = cli.value_of("queryfile").expect("A query file is required");
+    let queries = match read_input(path).map(|text| batch::parse(&text, "A")) {
+        Ok(Ok(queries)) if !queries.is_empty() => queries,
+        Ok(Ok(_)) => {
+            eprintln!("{}: no queries", path);
+            return 2;
+        }
+        Ok(Err(e)) => {
+            eprintln!("{}: {}", path, e);
+            return 2;
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return 2;
+        }
+    };
+    let server = match cli.value_of("server") {
+        Some(server) => server.to_string() + ":53",
+        None => resconf::get_resolver().to_string() + ":53",
+    };
+
+    let qps: f64 = match cli.value_of("qps").unwrap().parse() {
+        Ok(qps) if qps > 0.0 => qps,
+        _ => {
+            eprintln!("--qps must be a positive number");
+            return 2;
+        }
+    };
+    let count = match cli.value_of("count").map(str::parse) {
+        None => queries.len(),
+        Some(Ok(count)) => count,
+        Some(Err(_)) => {
+            eprintln!("--count must be a number");
+            return 2;
+        }
+    };
+    let (in_flight, timeout) = match (
+        cli.value_of("in-flight").unwrap().parse::<usize>(),
+        cli.value_of("timeout").unwrap().parse(),
+    ) {
+        (Ok(in_flight), Ok(timeout)) if in_flight > 0 => {
+            (in_flight, Duration::from_millis(timeout))
+        }
+        _ => {
+            eprintln!("--in-flight and --timeout must be positive numbers");
+            return 2;
+        }
+    };
+
+    let engine = match Engine::new(timeout) {
+        Ok(engine) => Arc::new(engine),
+        Err(e) => {
+            eprintln!("Failed to open a socket: {}", e);
+            return 2;
+        }
+    };
+    let report = bench::run(engine, queries, &server, qps, count, in_flight);
+    println!("{}", report);
+    if report.latencies.is_empty() || report.errors > 0 {
+        1
+    } else {
+        0
+    }
+}
+
+fn check_zone(cli: &ArgMatches) -> i32 {
+    let path = cli.value_of("zonefile").expect("A zone file is required");
+    let origin = cli.value_of("origin").unwrap_or(".");
+    let records = match zone::parse_file(path, origin) {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return 2;
+        }
+    };
+
+    let server = match cli.value_of("server") {
+        Some(server) => server.to_string() + ":53",
+        None => {
+            let resolver = resconf::get_resolver().to_string() + ":53";
+            match check::primary_master(&records, &resolver) {
+                Ok(server) => server,
+                Err(e) => {
+                    eprintln!("Failed to find the primary master: {}", e);
+                    return 2;
+                }
+            }
+        }
+    };
+
+    let findings = check::check_zone(&records, &server);
+    for finding in findings.iter() {
+        println!("{}", finding);
+    }
+    println!(
+        "{} records checked against {}, {} discrepancies",
+        records.len(),
+        server,
+        findings.len()
+    );
+    if findings.is_empty() {
+        0
+    } else {
+        1
+    }
+}
+
+fn check_spf(cli: &ArgMatches) -> i32 {
+    let domain = cli.value_of("domain").expect("A domain is required");
+    let ip: IpAddr = match cli.value_of("ip").expect("An IP is required").parse() {
+        Ok(ip) => ip,
+        Err(e) => {
+            eprintln!("Invalid IP address: {}", e);
+            return 2;
+        }
+    };
+    let server = match cli.value_of("server") {
+        Some(server) => server.to_string() + ":53",
+        None => resconf::get_resolver().to_string() + ":53",
+    };
+    let sender = cli.value_of("sender").unwrap_or("");
+    let resolver = match Recording::from_cli(cli) {
+        Ok(recording) => recording.resolver(server),
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    let evaluation = spf::check_host(&resolver, ip, domain, sender);
+    println!("{}", evaluation);
+    match evaluation.result {
+        spf::SpfResult::Pass => 0,
+        _ => 1,
+    }
+}
+
+fn check_mail_auth(cli: &ArgMatches) -> i32 {
+    let domain = cli.value_of("domain").expect("A domain is required");
+    let selectors: Vec<String> = cli
+        .values_of("selector")
+        .map(|values| values.map(String::from).collect())
+        .unwrap_or_default();
+    let server = match cli.value_of("server") {
+        Some(server) => server.to_string() + ":53",
+        None => resconf::get_resolver().to_string() + ":53",
+    };
+
+    let resolver = match Recording::from_cli(cli) {
+        Ok(recording) => recording.resolver(server),
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    let checks = mailauth::report(&resolver, domain, &selectors);
+    for check in checks.iter() {
+        println!("{}", check);
+    }
+    if checks.iter().all(|check| check.problems.is_empty()) {
+        0
+    } else {
+        1
+    }
+}
+
+fn read_pcap(cli: &ArgMatches) -> i32 {
+    let path = cli.value_of("capture").expect("A capture file is required");
+    let data = if path == "-" {
+        let mut data = Vec::new();
+        io::stdin().read_to_end(&mut data).map(|_| data)
+    } else {
+        fs::read(path)
+    };
+    let captured = match data.and_then(|data| pcap::read(&data)) {
+        Ok(captured) => captured,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return 2;
+        }
+    };
+
+    for packet in captured.iter() {
+        let when = packet.when.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let summary = format!(
+            ";; {}.{:06} {} > {} ({}), {} bytes",
+            when.as_secs(),
+            when.subsec_micros(),
+            packet.source,
+            packet.destination,
+            packet.transport,
+            packet.wire.len()
+        );
+        match packet.message() {
+            Ok(message) => println!("{}\n{}\n", summary, message),
+            Err(e) => println!("{}: {}\n", summary, e),
+        }
+    }
+    0
+}
+
+fn run_batch(
+    cli: &ArgMatches,
+    path: &str,
+    resolver: &str,
+    flags: QueryFlags,
+    recording: Recording,
+) -> i32 {
+    let text = match read_input(path) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return 2;
+        }
+    };
+
+    let default_qtype = cli.value_of("qtype").unwrap_or("A").to_uppercase();
+    let queries = match batch::parse(&text, &default_qtype) {
+        Ok(queries) => queries,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return 2;
+        }
+    };
+    let in_flight = match cli.value_of("in-flight").unwrap().parse() {
+        Ok(n) if n > 0 => n,
+        _ => {
+            eprintln!("--in-flight must be a positive number");
+            return 2;
+        }
+    };
+
+    let engine = match Engine::new(lud::QUERY_TIMEOUT) {
+        Ok(engine) => Arc::new(engine),
+        Err(e) => {
+            eprintln!("Failed to open a socket: {}", e);
+            return 2;
+        }
+    };
+
+    let json = cli.is_present("json");
+    let mut failures = 0;
+    batch::run(
+        queries,
+        resolver,
+        in_flight,
+        !cli.is_present("unordered"),
+        move |query, server| {
+            let q_message = flags.query(query.name.clone(), query.qtype.clone());
+            recording.lookup(q_message, server, |q_message| {
+                engine.lookup_message(q_message, server)
+            })
+        },
+        |query, result| match result {
+            Ok(response) if json => println!("{}", response.to_json()),
+            Ok(response) => println!(";; {}\n{}\n", query, response),
+            Err(e) => {
+                failures += 1;
+                if json {
+                    println!(
+                        "{{\"query\":{},\"error\":{}}}",
+                        json_string(&query.to_string()),
+                        json_string(&e.to_string())
+                    );
+                } else {
+                    println!(";; {}: {}\n", query, e);
+                }
+            }
+        },
+    );
+    if failures == 0 {
+        0
+    } else {
+        1
+    }
+}
+
 fn main() {
     let cli = parse_cli();
 
-    let name = cli
-        .value_of("name")
-        .map(String::from)
-        .expect("A name to lookup is required");
+    if let Some(bench_cli) = cli.subcommand_matches("bench") {
+        process::exit(run_bench(bench_cli));
+    }
+    if let Some(check_cli) = cli.subcommand_matches("check-zone") {
+        process::exit(check_zone(check_cli));
+    }
+    if let Some(mail_cli) = cli.subcommand_matches("mail-auth") {
+        process::exit(check_mail_auth(mail_cli));
+    }
+    if let Some(pcap_cli) = cli.subcommand_matches("pcap") {
+        process::exit(read_pcap(pcap_cli));
+    }
+    if let Some(spf_cli) = cli.subcommand_matches("spf") {
+        process::exit(check_spf(spf_cli));
+    }
+
+    let resolver = match cli.value_of("server") {
+        Some(server) => server.to_string() + ":53",
+        None => resconf::get_resolver().to_string() + ":53",
+    };
+    let flags = match QueryFlags::from_cli(&cli) {
+        Ok(flags) => flags,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+    let recording = match Recording::from_cli(&cli) {
+        Ok(recording) => recording,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+    if let Some(path) = cli.value_of("file") {
+        process::exit(run_batch(&cli, path, &resolver, flags, recording));
+    }
+
+    let (names, default_qtype) = match cli.value_of("reverse") {
+        Some(address) => match reverse::name_for(address) {
+            Ok(name) => (vec![name], "PTR"),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(2);
+            }
+        },
+        None => (
+            cli.values_of("name")
+                .expect("A name to lookup is required")
+                .map(String::from)
+                .collect::<Vec<_>>(),
+            "A",
+        ),
+    };
     let qtype = cli
         .value_of("qtype")
         .map(String::from)
-        .unwrap_or("A".to_string());
-    let resolver;
-    match cli.value_of("server").map(String::from) {
-        Some(server) => {
-            resolver = server + ":53";
-        }
-        _ => {
-            resolver = resconf::get_resolver().to_string() + ":53";
+        .unwrap_or(default_qtype.to_string());
+    let parsed_qtype = match QType::try_from(qtype.clone()) {
+        Ok(parsed) => parsed,
+        Err(_) => {
+            eprintln!("unknown type {}", qtype);
+            process::exit(2);
         }
+    };
+    let mut q_message = flags.query(names[0].clone(), qtype);
+    for name in names[1..].iter() {
+        q_message = q_message.with_question(parse_name(name.clone()), parsed_qtype);
     }
-    let raw = cli.is_present("raw");
-    let mut recv_buf = [0u8; message::DNS_MSG_MAX];
-    let send_res = send_query(&mut recv_buf, name, qtype, resolver);
-    let received = send_res.unwrap();
-    if raw {
+    if cli.is_present("raw") {
+        let mut recv_buf = [0u8; message::DNS_MSG_MAX];
+        let received = send_message(&mut recv_buf, &q_message, resolver).unwrap();
         let mut sep = "";
         for (i, b) in recv_buf[..received].iter().enumerate() {
             if i % 2 == 0 {
@@ -79,8 +808,22 @@ fn main() {
         process::exit(0);
     }
 
-    match message::Message::from_wire(&recv_buf[..received]) {
-        Ok(message) => println!("{}", message),
-        Err(e) => println!("Failed to parse message: {:?}", e),
+    let server = resolver.clone();
+    match recording.lookup(q_message, &server, |q_message| {
+        query_message(&q_message, resolver)
+    }) {
+        Ok(response) if cli.is_present("json") => println!("{}", response.to_json()),
+        Ok(response) if cli.is_present("dig") => {
+            let exchange = dig::Exchange {
+                server: &response.server,
+                transport: response.transport,
+                query_time: response.elapsed,
+                when: response.when,
+                msg_size: response.size(),
+            };
+            print!("{}", dig::format(&response.message, &exchange));
+        }
+        Ok(response) => println!("{}", response),
+        Err(e) => println!("Failed to query: {}", e),
     }
 }
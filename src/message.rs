@@ -1,17 +1,24 @@
 use byteorder::{BigEndian, ReadBytesExt};
-use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::error;
 use std::fmt;
 
 use rand::random;
 
+use crate::compress::{Compression, NameCompressor};
+use crate::edns::{Edns, DEFAULT_UDP_PAYLOAD};
+use crate::errors::ParseError;
 use crate::question::{QType, Question};
-use crate::rr::RR;
+use crate::rdata::RData;
+use crate::rr::{Class, RRType, RR};
+use crate::ttl::Ttl;
+use crate::utils::{byte_combine, TRUNCATED};
 
-pub const DNS_MSG_MAX: usize = 512;
+/// The largest reply we accept over UDP; with EDNS, replies may exceed the
+/// 512 octets of RFC 1035.
+pub const DNS_MSG_MAX: usize = 4096;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RCode {
     NoError = 0,
     FormatError = 1,
@@ -36,7 +43,7 @@ impl From<u16> for RCode {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum QR {
     Q,
     R,
@@ -51,6 +58,7 @@ impl From<bool> for QR {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct MessageMeta {
     pub qr: QR,
     pub opcode: u8,
@@ -85,7 +93,7 @@ impl MessageMeta {
         byte_1 += self.rd as u8;
 
         let mut byte_2 = (*&self.ra as u8) << 7;
-        byte_2 += (self.z as u8) << 2;
+        byte_2 += (self.z as u8) << 4;
         byte_2 += *&self.rcode as u8;
 
         vec![byte_1, byte_2]
@@ -102,6 +110,7 @@ impl fmt::Display for MessageMeta {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Message {
     pub id: u16,
     pub meta: MessageMeta,
@@ -113,6 +122,7 @@ pub struct Message {
     pub answer: Vec<RR>,
     pub authority: Vec<RR>,
     pub additional: Vec<RR>,
+    pub edns: Option<Edns>,
 }
 
 impl fmt::Display for Message {
@@ -137,7 +147,8 @@ impl fmt::Display for Message {
         ]
         .iter()
         {
-            if section.0 > 0 {
+            // the OPT pseudo-RR is counted in ARCOUNT but shown on its own
+            if section.0 > 0 && !section.1.is_empty() {
                 let mut fmt_section = Vec::new();
                 for item in section.1.iter() {
                     fmt_section.push(item.to_string());
@@ -146,12 +157,19 @@ impl fmt::Display for Message {
             }
         }
 
+        if let Some(edns) = &self.edns {
+            to_write.push_str(&format!("\n\nEDNS\n{}", edns));
+        }
+
         write!(f, "{}", to_write)
     }
 }
 
 impl Message {
     pub fn from_wire(buf: &[u8]) -> Result<Message, &dyn error::Error> {
+        if buf.len() < 12 {
+            return Err(TRUNCATED);
+        }
         let mut m_reply: &[u8] = &buf[..buf.len()];
         let mut message = Message {
             id: m_reply.read_u16::<BigEndian>().unwrap(),
@@ -164,21 +182,30 @@ impl Message {
             answer: Vec::new(),
             authority: Vec::new(),
             additional: Vec::new(),
+            edns: None,
         };
 
         let (question, offset) = extract_questions(&buf, 12, message.qdcount)?;
         let (answer, offset) = extract_rrset(&buf, offset, message.ancount)?;
         let (authority, offset) = extract_rrset(&buf, offset, message.nscount)?;
-        let (additional, _offset) = extract_rrset(&buf, offset, message.arcount)?;
+        let (additional, edns, _offset) = extract_additional(&buf, offset, message.arcount)?;
 
         message.question = question;
         message.answer = answer;
         message.authority = authority;
         message.additional = additional;
+        message.edns = edns;
         Ok(message)
     }
 
+    /// The message as sent, with names compressed.
     pub fn to_wire(&self) -> Vec<u8> {
+        self.to_wire_with(Compression::Enabled)
+    }
+
+    /// The message with names compressed, written in full, or written in
+    /// full and lowercased.
+    pub fn to_wire_with(&self, compression: Compression) -> Vec<u8> {
         let mut wire = Vec::new();
         wire.push((self.id >> 8) as u8);
         wire.push((self.id & 255) as u8);
@@ -191,35 +218,27 @@ impl Message {
             wire.push(f as u8);
         }
 
-        let mut offset_map = HashMap::new();
+        let mut names = NameCompressor::new(compression);
         for q in self.question.iter() {
-            for index in 0..q.qname.len() {
-                let name = q.qname[index..].join(".");
-                match offset_map.get(&name) {
-                    Some(offset) => {
-                        let flagged_offset = 0b11000000_00000000 + offset;
-                        wire.push((flagged_offset >> 8) as u8);
-                        wire.push((flagged_offset & 255) as u8);
-                        let qtype_wire: u16 = u16::from(q.qtype);
-                        wire.push((qtype_wire >> 8) as u8);
-                        wire.push((qtype_wire & 255) as u8);
-                    }
-                    None => {
-                        offset_map.insert(name, wire.len());
-                        wire.push(q.qname[index].len() as u8);
-                        for byte in q.qname[index].bytes() {
-                            wire.push(byte);
-                        }
-                    }
-                }
-            }
+            names.write_name(&mut wire, &q.qname);
             let qtype_wire: u16 = u16::from(q.qtype);
             wire.push((qtype_wire >> 8) as u8);
             wire.push((qtype_wire & 255) as u8);
             wire.push((q.qclass >> 8) as u8);
             wire.push((q.qclass & 255) as u8);
         }
+        for rr in self
+            .answer
+            .iter()
+            .chain(self.authority.iter())
+            .chain(self.additional.iter())
+        {
+            rr.write_wire(&mut wire, &mut names);
+        }
 
+        if let Some(edns) = &self.edns {
+            wire.extend_from_slice(&edns.to_wire());
+        }
         wire
     }
 
@@ -240,8 +259,92 @@ impl Message {
             answer: Vec::<RR>::new(),
             authority: Vec::<RR>::new(),
             additional: Vec::<RR>::new(),
+            edns: None,
         }
     }
+
+    /// Ask the questions in `class` instead of IN.
+    pub fn with_class(mut self, class: Class) -> Message {
+        for q in self.question.iter_mut() {
+            q.qclass = class as u16;
+        }
+        self
+    }
+
+    /// Add another question, in the class of the first one.
+    pub fn with_question(mut self, name: Vec<String>, qtype: QType) -> Message {
+        let qclass = self.question.first().map_or(Class::IN as u16, |q| q.qclass);
+        self.question.push(Question::new(name, qtype, qclass));
+        self.qdcount += 1;
+        self
+    }
+
+    /// Set or clear Recursion Desired.
+    pub fn with_rd(mut self, rd: bool) -> Message {
+        self.meta.rd = rd;
+        self
+    }
+
+    /// Set or clear Authentic Data (RFC 6840 §5.7: ask for the AD bit in
+    /// the reply).
+    pub fn with_ad(mut self, ad: bool) -> Message {
+        self.meta.z = if ad {
+            self.meta.z | 0b010
+        } else {
+            self.meta.z & !0b010
+        };
+        self
+    }
+
+    /// Set or clear Checking Disabled.
+    pub fn with_cd(mut self, cd: bool) -> Message {
+        self.meta.z = if cd {
+            self.meta.z | 0b001
+        } else {
+            self.meta.z & !0b001
+        };
+        self
+    }
+
+    pub fn with_opcode(mut self, opcode: u8) -> Message {
+        self.meta.opcode = opcode & 0b1111;
+        self
+    }
+
+    /// Add an OPT record advertising the given UDP payload size, or change
+    /// the size of the one already there.
+    pub fn with_edns(mut self, udp_payload_size: u16) -> Message {
+        match &mut self.edns {
+            Some(edns) => edns.udp_payload_size = udp_payload_size,
+            None => {
+                self.edns = Some(Edns::new(udp_payload_size));
+                self.arcount += 1;
+            }
+        }
+        self
+    }
+
+    /// Set or clear DNSSEC OK, which lives in the OPT record; one is added
+    /// when the flag is set.
+    pub fn with_dnssec_ok(mut self, dnssec_ok: bool) -> Message {
+        if dnssec_ok && self.edns.is_none() {
+            self = self.with_edns(DEFAULT_UDP_PAYLOAD);
+        }
+        if let Some(edns) = &mut self.edns {
+            edns.dnssec_ok = dnssec_ok;
+        }
+        self
+    }
+
+    /// How long a negative answer (NXDOMAIN or NODATA) may be cached: the
+    /// smaller of the authority SOA's own TTL and its MINIMUM field
+    /// (RFC 2308 §5).  None when there is no SOA to go by.
+    pub fn negative_ttl(&self) -> Option<Ttl> {
+        self.authority.iter().find_map(|rr| match rr.rdata() {
+            RData::SOA(soa) => Some(rr.ttl().min(soa.minimum())),
+            _ => None,
+        })
+    }
 }
 
 fn extract_rrset(
@@ -262,6 +365,39 @@ fn extract_rrset(
     Ok((rrset, idx)) // index of next section
 }
 
+fn extract_additional(
+    buf: &[u8],
+    offset: usize,
+    rrcount: u16,
+) -> Result<(Vec<RR>, Option<Edns>, usize), &dyn error::Error> {
+    let mut idx = offset;
+    let mut processed_rrs = 0;
+    let mut rrset = Vec::new();
+    let mut edns = None;
+
+    while processed_rrs < rrcount {
+        // an OPT record is always owned by the root name
+        let opt = buf.get(idx..idx + 3).is_some_and(|start| {
+            start[0] == 0 && byte_combine(start[1], start[2]) == u16::from(RRType::OPT)
+        });
+        if opt {
+            // there may be only one (RFC 6891 §6.1.1)
+            if edns.is_some() {
+                return Err(&ParseError::InvalidRData);
+            }
+            let (opt, l_idx) = Edns::from_wire(buf, idx + 1)?;
+            edns = Some(opt);
+            idx = l_idx;
+        } else {
+            let (rr, l_idx) = RR::from_wire(buf, idx)?;
+            rrset.push(rr);
+            idx = l_idx;
+        }
+        processed_rrs += 1;
+    }
+    Ok((rrset, edns, idx))
+}
+
 fn extract_questions(
     reply: &[u8],
     mut offset: usize,
@@ -299,5 +435,144 @@ mod tests {
             (META_STD_RD_QUERY & 255) as u8,
         ];
         assert_eq!(mm.to_wire(), expected_wire);
+
+        // RA, AD and CD
+        let mm = MessageMeta::new(0b1000_0000_1011_0000);
+        assert_eq!(mm.to_wire(), vec![0b1000_0000, 0b1011_0000]);
+    }
+
+    #[test]
+    fn message_questions() {
+        let labels = |name: &str| name.split('.').map(String::from).collect::<Vec<_>>();
+        let query = Message::new(labels("version.bind."), "TXT".to_string())
+            .with_class(Class::CH)
+            .with_question(labels("hostname.bind."), QType::RRType(RRType::TXT));
+        let wire = query.to_wire();
+        // the second name is "hostname" and a pointer to "bind"
+        assert_eq!(
+            wire[12 + 14..],
+            [
+                0x00, 0x10, 0x00, 0x03, 0x08, b'h', b'o', b's', b't', b'n', b'a', b'm', b'e', 0xc0,
+                0x14, 0x00, 0x10, 0x00, 0x03
+            ]
+        );
+
+        let parsed = Message::from_wire(&wire).unwrap();
+        assert_eq!(parsed.qdcount, 2);
+        assert_eq!(parsed.question[1].qname[0], "hostname");
+        assert_eq!(parsed.question[1].qclass, Class::CH as u16);
+    }
+
+    #[test]
+    fn message_compression() {
+        let labels = |name: &str| name.split('.').map(String::from).collect::<Vec<_>>();
+        let mut reply = Message::new(labels("Example.com."), "MX".to_string());
+        let tokens = vec!["10".to_string(), "mail.Example.com.".to_string()];
+        let mx = RData::from_presentation(RRType::MX, &tokens, &[]).unwrap();
+        reply.answer.push(RR::new(
+            labels("Example.com."),
+            RRType::MX,
+            Class::IN,
+            Ttl::from_secs(300),
+            mx,
+        ));
+        reply.ancount = 1;
+
+        // the owner is a pointer to the question, the exchange ends in one
+        let wire = reply.to_wire();
+        assert_eq!(
+            wire[29..],
+            [
+                0xc0, 0x0c, 0x00, 0x0f, 0x00, 0x01, 0x00, 0x00, 0x01, 0x2c, 0x00, 0x09, 0x00, 0x0a,
+                0x04, b'm', b'a', b'i', b'l', 0xc0, 0x0c
+            ]
+        );
+        let parsed = Message::from_wire(&wire).unwrap();
+        assert_eq!(
+            parsed.answer[0].rdata().to_presentation(),
+            "10 mail.Example.com."
+        );
+
+        let full = reply.to_wire_with(Compression::Disabled);
+        assert_eq!(full.len(), 29 + 13 + 10 + 20);
+        assert_eq!(full[29..42], *b"\x07Example\x03com\x00");
+        let canonical = reply.to_wire_with(Compression::Canonical);
+        assert_eq!(canonical[29..42], *b"\x07example\x03com\x00");
+        assert_eq!(canonical[54..], *b"\x04mail\x07example\x03com\x00");
+    }
+
+    #[test]
+    fn message_negative_ttl() {
+        let labels = |name: &str| name.split('.').map(String::from).collect::<Vec<_>>();
+        let mut reply = Message::new(labels("nx.example.com."), "A".to_string());
+        assert_eq!(reply.negative_ttl(), None);
+
+        let tokens: Vec<String> =
+            "ns1.example.com. hostmaster.example.com. 1 7200 3600 1209600 300"
+                .split(' ')
+                .map(String::from)
+                .collect();
+        let soa = RData::from_presentation(RRType::SOA, &tokens, &[]).unwrap();
+        let soa_rr = |ttl| {
+            RR::new(
+                labels("example.com."),
+                RRType::SOA,
+                Class::IN,
+                ttl,
+                soa.clone(),
+            )
+        };
+        reply.authority.push(soa_rr(Ttl::from_secs(3600)));
+        assert_eq!(reply.negative_ttl(), Some(Ttl::from_secs(300)));
+        reply.authority[0] = soa_rr(Ttl::from_secs(60));
+        assert_eq!(reply.negative_ttl(), Some(Ttl::from_secs(60)));
+    }
+
+    #[test]
+    fn message_flags() {
+        let labels = vec!["example".to_string(), "".to_string()];
+        let query = Message::new(labels, "A".to_string())
+            .with_rd(false)
+            .with_cd(true)
+            .with_ad(true)
+            .with_opcode(4)
+            .with_dnssec_ok(true);
+        let wire = query.to_wire();
+        assert_eq!(wire[2..4], [0b0010_0000, 0b0011_0000]);
+
+        let parsed = Message::from_wire(&wire).unwrap();
+        assert!(!parsed.meta.rd);
+        assert_eq!(parsed.meta.opcode, 4);
+        assert_eq!(parsed.meta.z, 0b011);
+        assert_eq!(parsed.arcount, 1);
+        let edns = parsed.edns.unwrap();
+        assert!(edns.dnssec_ok);
+        assert_eq!(edns.udp_payload_size, DEFAULT_UDP_PAYLOAD);
+
+        let query = Message::new(vec!["".to_string()], "NS".to_string())
+            .with_dnssec_ok(true)
+            .with_dnssec_ok(false);
+        assert!(!query.edns.unwrap().dnssec_ok);
+    }
+
+    #[test]
+    fn message_unknown_types() {
+        // a DS answer with its RRSIG, neither of which is decoded
+        let wire = [
+            0x12, 0x34, 0x81, 0x80, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x07, b'e',
+            b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00, 0x00, 0x2b, 0x00,
+            0x01, 0xc0, 0x0c, 0x00, 0x2b, 0x00, 0x01, 0x00, 0x00, 0x0e, 0x10, 0x00, 0x04, 0xde,
+            0xad, 0xbe, 0xef, 0xc0, 0x0c, 0x00, 0x2e, 0x00, 0x01, 0x00, 0x00, 0x0e, 0x10, 0x00,
+            0x06, 0x00, 0x2b, 0x0d, 0x02, 0x00, 0x00,
+        ];
+        let parsed = Message::from_wire(&wire).unwrap();
+        assert_eq!(parsed.question[0].qtype, QType::RRType(RRType::Unknown(43)));
+        assert_eq!(parsed.answer[0].rrtype(), RRType::Unknown(43));
+        assert_eq!(parsed.answer[1].rrtype(), RRType::Unknown(46));
+        assert_eq!(
+            parsed.answer[1].rdata(),
+            &RData::UNKNOWN(46, vec![0x00, 0x2b, 0x0d, 0x02, 0x00, 0x00])
+        );
+        assert_eq!(parsed.to_wire(), wire.to_vec());
     }
 }
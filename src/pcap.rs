@@ -0,0 +1,777 @@
+/* DNS messages in packet captures.  Reading takes pcap or pcapng files,
+ * picks out UDP and TCP traffic to or from port 53, puts TCP streams back
+ * together and splits them into messages (RFC 1035 §4.2.2).  Writing puts
+ * lud's own queries and replies in a pcap file as raw IP packets, which
+ * Wireshark and tcpdump can read. */
+
+use std::collections::{BTreeMap, HashMap};
+use std::error;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::message::Message;
+use crate::recording::Record;
+use crate::response::{Response, Transport};
+
+const DNS_PORT: u16 = 53;
+
+const PCAP_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_NANOS: u32 = 0xa1b2_3c4d;
+const PCAPNG_SECTION: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER: u32 = 0x1a2b_3c4d;
+
+/* link layer types, from https://www.tcpdump.org/linktypes.html */
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LOOP: u32 = 108;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+
+/// A DNS message found in a capture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Captured {
+    /// When the packet holding the message (or its last part, over TCP)
+    /// was captured.
+    pub when: SystemTime,
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+    pub transport: Transport,
+    pub wire: Vec<u8>,
+}
+
+impl Captured {
+    pub fn message(&self) -> Result<Message, &dyn error::Error> {
+        Message::from_wire(&self.wire)
+    }
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.to_string())
+}
+
+fn u16_at(data: &[u8], at: usize, little: bool) -> Option<u16> {
+    let bytes = [*data.get(at)?, *data.get(at + 1)?];
+    Some(if little {
+        u16::from_le_bytes(bytes)
+    } else {
+        u16::from_be_bytes(bytes)
+    })
+}
+
+fn u32_at(data: &[u8], at: usize, little: bool) -> Option<u32> {
+    let bytes = [
+        *data.get(at)?,
+        *data.get(at + 1)?,
+        *data.get(at + 2)?,
+        *data.get(at + 3)?,
+    ];
+    Some(if little {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    })
+}
+
+/// A captured link layer frame.
+struct Frame<'a> {
+    when: SystemTime,
+    linktype: u32,
+    data: &'a [u8],
+}
+
+/// The frames of a classic pcap file.  A record cut short, as when the
+/// capture was interrupted, ends the file.
+fn pcap_frames(data: &[u8]) -> io::Result<Vec<Frame<'_>>> {
+    let (little, nanos) = match (u32_at(data, 0, true), u32_at(data, 0, false)) {
+        (Some(PCAP_MICROS), _) => (true, false),
+        (Some(PCAP_NANOS), _) => (true, true),
+        (_, Some(PCAP_MICROS)) => (false, false),
+        (_, Some(PCAP_NANOS)) => (false, true),
+        _ => return Err(invalid("not a pcap file")),
+    };
+    let linktype = u32_at(data, 20, little).ok_or_else(|| invalid("truncated pcap header"))?;
+
+    let mut frames = Vec::new();
+    let mut at = 24;
+    while let Some(captured) = u32_at(data, at + 8, little) {
+        let start = at + 16;
+        let end = start + captured as usize;
+        if end > data.len() {
+            break;
+        }
+        let secs = u32_at(data, at, little).unwrap_or(0) as u64;
+        let fraction = u32_at(data, at + 4, little).unwrap_or(0) as u64;
+        let since_epoch = if nanos {
+            Duration::from_secs(secs) + Duration::from_nanos(fraction)
+        } else {
+            Duration::from_secs(secs) + Duration::from_micros(fraction)
+        };
+        frames.push(Frame {
+            when: UNIX_EPOCH + since_epoch,
+            linktype,
+            data: &data[start..end],
+        });
+        at = end;
+    }
+    Ok(frames)
+}
+
+/// Convert a pcapng timestamp in `units` per second.
+fn pcapng_time(ticks: u64, units: u64) -> SystemTime {
+    let nanos = (ticks % units) as u128 * 1_000_000_000 / units as u128;
+    UNIX_EPOCH + Duration::from_secs(ticks / units) + Duration::from_nanos(nanos as u64)
+}
+
+/// The timestamp resolution of an interface, in units per second, from
+/// the options of its description block.
+fn pcapng_resolution(options: &[u8], little: bool) -> u64 {
+    let mut at = 0;
+    while let (Some(code), Some(length)) =
+        (u16_at(options, at, little), u16_at(options, at + 2, little))
+    {
+        let value = at + 4;
+        match code {
+            0 => break,
+            9 if length == 1 => {
+                let exponent = options.get(value).cloned().unwrap_or(6);
+                // the top bit picks a power of 2 rather than of 10
+                return if exponent & 0x80 != 0 {
+                    1u64.checked_shl((exponent & 0x7f) as u32).unwrap_or(1)
+                } else {
+                    10u64.checked_pow(exponent as u32).unwrap_or(1)
+                };
+            }
+            _ => (),
+        }
+        at = value + (length as usize).div_ceil(4) * 4;
+    }
+    1_000_000
+}
+
+/// The frames of a pcapng file, which may hold several sections.
+fn pcapng_frames(data: &[u8]) -> io::Result<Vec<Frame<'_>>> {
+    let mut frames = Vec::new();
+    let mut little = true;
+    // the link type and timestamp resolution of each interface
+    let mut interfaces: Vec<(u32, u64)> = Vec::new();
+
+    let mut at = 0;
+    while at + 12 <= data.len() {
+        let block_type = u32_at(data, at, little).unwrap_or(0);
+        if block_type == PCAPNG_SECTION {
+            little = match u32_at(data, at + 8, true) {
+                Some(PCAPNG_BYTE_ORDER) => true,
+                _ if u32_at(data, at + 8, false) == Some(PCAPNG_BYTE_ORDER) => false,
+                _ => return Err(invalid("bad pcapng byte order magic")),
+            };
+            interfaces.clear();
+        }
+        let length = u32_at(data, at + 4, little).unwrap_or(0) as usize;
+        if length < 12 || !length.is_multiple_of(4) || at + length > data.len() {
+            break;
+        }
+        let body = &data[at + 8..at + length - 4];
+
+        match block_type {
+            // interface description
+            1 => {
+                let linktype = u16_at(body, 0, little).unwrap_or(0) as u32;
+                let resolution = pcapng_resolution(body.get(8..).unwrap_or(&[]), little);
+                interfaces.push((linktype, resolution));
+            }
+            // enhanced packet
+            6 => {
+                let interface = u32_at(body, 0, little).unwrap_or(0) as usize;
+                let high = u32_at(body, 4, little).unwrap_or(0) as u64;
+                let low = u32_at(body, 8, little).unwrap_or(0) as u64;
+                let captured = u32_at(body, 12, little).unwrap_or(0) as usize;
+                if let (Some(&(linktype, resolution)), Some(packet)) =
+                    (interfaces.get(interface), body.get(20..20 + captured))
+                {
+                    frames.push(Frame {
+                        when: pcapng_time(high << 32 | low, resolution),
+                        linktype,
+                        data: packet,
+                    });
+                }
+            }
+            // simple packet, which has no timestamp and is from the first
+            // interface
+            3 => {
+                let original = u32_at(body, 0, little).unwrap_or(0) as usize;
+                let packet = &body[4.min(body.len())..];
+                if let Some(&(linktype, _)) = interfaces.first() {
+                    frames.push(Frame {
+                        when: UNIX_EPOCH,
+                        linktype,
+                        data: &packet[..original.min(packet.len())],
+                    });
+                }
+            }
+            _ => (),
+        }
+        at += length;
+    }
+    Ok(frames)
+}
+
+/// The IP packet in a frame, if it holds one.
+fn ip_packet<'a>(frame: &Frame<'a>) -> Option<&'a [u8]> {
+    let data = frame.data;
+    match frame.linktype {
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => Some(data),
+        LINKTYPE_NULL | LINKTYPE_LOOP => data.get(4..),
+        LINKTYPE_LINUX_SLL => data.get(16..),
+        LINKTYPE_LINUX_SLL2 => data.get(20..),
+        LINKTYPE_ETHERNET => {
+            let mut at = 12;
+            // skip 802.1Q and 802.1ad tags
+            while let Some(0x8100) | Some(0x88a8) = u16_at(data, at, false) {
+                at += 4;
+            }
+            match u16_at(data, at, false)? {
+                0x0800 | 0x86dd => data.get(at + 2..),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// A TCP or UDP segment found in an IP packet.
+struct Segment<'a> {
+    protocol: u8,
+    source: IpAddr,
+    destination: IpAddr,
+    payload: &'a [u8],
+}
+
+/// The transport layer segment in an IP packet.  Fragments are skipped.
+fn ip_segment(packet: &[u8]) -> Option<Segment<'_>> {
+    match packet.first()? >> 4 {
+        4 => {
+            let header = ((packet[0] & 0x0f) as usize) * 4;
+            let total = u16_at(packet, 2, false)? as usize;
+            // more fragments, or a fragment offset
+            if u16_at(packet, 6, false)? & 0x3fff != 0 || header < 20 || total < header {
+                return None;
+            }
+            let mut source = [0; 4];
+            source.copy_from_slice(packet.get(12..16)?);
+            let mut destination = [0; 4];
+            destination.copy_from_slice(packet.get(16..20)?);
+            Some(Segment {
+                protocol: packet[9],
+                source: IpAddr::from(source),
+                destination: IpAddr::from(destination),
+                // frames may be padded beyond the end of the packet
+                payload: packet.get(header..total.min(packet.len()))?,
+            })
+        }
+        6 => {
+            let length = u16_at(packet, 4, false)? as usize;
+            let mut source = [0; 16];
+            source.copy_from_slice(packet.get(8..24)?);
+            let mut destination = [0; 16];
+            destination.copy_from_slice(packet.get(24..40)?);
+            let mut protocol = packet[6];
+            let mut at = 40;
+            loop {
+                match protocol {
+                    // hop-by-hop, routing and destination options
+                    0 | 43 | 60 => {
+                        protocol = *packet.get(at)?;
+                        at += (*packet.get(at + 1)? as usize + 1) * 8;
+                    }
+                    // authentication header
+                    51 => {
+                        protocol = *packet.get(at)?;
+                        at += (*packet.get(at + 1)? as usize + 2) * 4;
+                    }
+                    // fragment, or no next header
+                    44 | 59 => return None,
+                    _ => break,
+                }
+            }
+            Some(Segment {
+                protocol,
+                source: IpAddr::from(source),
+                destination: IpAddr::from(destination),
+                payload: packet.get(at..(40 + length).min(packet.len()))?,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// One direction of a TCP connection, put back in order.
+#[derive(Default)]
+struct Stream {
+    /// The sequence number of the first byte of the stream.
+    start: u32,
+    /// How many bytes have been put in order so far.
+    assembled: u32,
+    /// Segments that arrived ahead of a gap, by offset in the stream.
+    ahead: BTreeMap<u32, Vec<u8>>,
+    /// Bytes in order not yet split into messages.
+    buffer: Vec<u8>,
+}
+
+impl Stream {
+    fn new(start: u32) -> Stream {
+        Stream {
+            start,
+            ..Stream::default()
+        }
+    }
+
+    /// Add the payload of a segment, then take out the messages that are
+    /// complete: each is preceded by its length in two octets.
+    fn add(&mut self, seq: u32, payload: &[u8]) -> Vec<Vec<u8>> {
+        let offset = seq.wrapping_sub(self.start);
+        // retransmissions of what came before the start of the stream
+        // wrap around to huge offsets
+        if !payload.is_empty() && offset < 1 << 31 {
+            let longest = self.ahead.get(&offset).map_or(0, Vec::len);
+            if payload.len() > longest {
+                self.ahead.insert(offset, payload.to_vec());
+            }
+        }
+        while let Some((&offset, _)) = self.ahead.iter().next() {
+            if offset > self.assembled {
+                break;
+            }
+            let data = self.ahead.remove(&offset).unwrap_or_default();
+            let skip = (self.assembled - offset) as usize;
+            if skip < data.len() {
+                self.buffer.extend_from_slice(&data[skip..]);
+                self.assembled = offset + data.len() as u32;
+            }
+        }
+
+        let mut messages = Vec::new();
+        while let Some(length) = u16_at(&self.buffer, 0, false) {
+            let end = 2 + length as usize;
+            if self.buffer.len() < end {
+                break;
+            }
+            messages.push(self.buffer[2..end].to_vec());
+            self.buffer.drain(..end);
+        }
+        messages
+    }
+}
+
+/// The DNS messages in a capture, in the order they were completed.
+pub fn read(data: &[u8]) -> io::Result<Vec<Captured>> {
+    let frames = if u32_at(data, 0, true) == Some(PCAPNG_SECTION) {
+        pcapng_frames(data)?
+    } else {
+        pcap_frames(data)?
+    };
+
+    let mut captured = Vec::new();
+    let mut streams: HashMap<(SocketAddr, SocketAddr), Stream> = HashMap::new();
+    for frame in frames.iter() {
+        let segment = match ip_packet(frame).and_then(ip_segment) {
+            Some(segment) => segment,
+            None => continue,
+        };
+        let payload = segment.payload;
+        let (source_port, destination_port) =
+            match (u16_at(payload, 0, false), u16_at(payload, 2, false)) {
+                (Some(source), Some(destination)) => (source, destination),
+                _ => continue,
+            };
+        if source_port != DNS_PORT && destination_port != DNS_PORT {
+            continue;
+        }
+        let source = SocketAddr::new(segment.source, source_port);
+        let destination = SocketAddr::new(segment.destination, destination_port);
+
+        match segment.protocol {
+            IPPROTO_UDP => {
+                let length = u16_at(payload, 4, false).unwrap_or(0) as usize;
+                if let Some(wire) = payload.get(8..length.min(payload.len())) {
+                    captured.push(Captured {
+                        when: frame.when,
+                        source,
+                        destination,
+                        transport: Transport::Udp,
+                        wire: wire.to_vec(),
+                    });
+                }
+            }
+            IPPROTO_TCP => {
+                let (seq, header, flags) =
+                    match (u32_at(payload, 4, false), payload.get(12), payload.get(13)) {
+                        (Some(seq), Some(offset), Some(&flags)) => {
+                            (seq, (offset >> 4) as usize * 4, flags)
+                        }
+                        _ => continue,
+                    };
+                let data = match payload.get(header..) {
+                    Some(data) if header >= 20 => data,
+                    _ => continue,
+                };
+                // a SYN starts the stream afresh, its data one past the
+                // SYN's sequence number; without one, the stream starts
+                // with the first segment seen
+                let syn = flags & 0x02 != 0;
+                let seq = if syn { seq.wrapping_add(1) } else { seq };
+                if syn {
+                    streams.insert((source, destination), Stream::new(seq));
+                }
+                let stream = streams
+                    .entry((source, destination))
+                    .or_insert_with(|| Stream::new(seq));
+                for wire in stream.add(seq, data) {
+                    captured.push(Captured {
+                        when: frame.when,
+                        source,
+                        destination,
+                        transport: Transport::Tcp,
+                        wire,
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(captured)
+}
+
+pub fn read_file(path: &str) -> io::Result<Vec<Captured>> {
+    read(&fs::read(path)?)
+}
+
+/// The Internet checksum (RFC 1071) of `data`.
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]) as u32)
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Writes UDP packets to a pcap file.
+pub struct PcapWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Start a capture on `out`, writing its header.
+    pub fn new(mut out: W) -> io::Result<PcapWriter<W>> {
+        let mut header = PCAP_MICROS.to_le_bytes().to_vec();
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&4u16.to_le_bytes());
+        header.extend_from_slice(&[0; 8]);
+        header.extend_from_slice(&65535u32.to_le_bytes());
+        header.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+        out.write_all(&header)?;
+        Ok(PcapWriter { out })
+    }
+
+    /// Write a UDP packet carrying `payload`.
+    pub fn write_udp(
+        &mut self,
+        when: SystemTime,
+        source: SocketAddr,
+        destination: SocketAddr,
+        payload: &[u8],
+    ) -> io::Result<()> {
+        let too_big = || io::Error::new(io::ErrorKind::InvalidInput, "payload too big for UDP");
+        let udp_length = payload.len() + 8;
+        if udp_length > 0xffff - 40 {
+            return Err(too_big());
+        }
+        let mut udp = source.port().to_be_bytes().to_vec();
+        udp.extend_from_slice(&destination.port().to_be_bytes());
+        udp.extend_from_slice(&(udp_length as u16).to_be_bytes());
+        udp.extend_from_slice(&[0, 0]);
+        udp.extend_from_slice(payload);
+
+        let (mut packet, mut pseudo_header) = match (source.ip(), destination.ip()) {
+            (IpAddr::V4(from), IpAddr::V4(to)) => {
+                let mut ip = vec![0x45, 0];
+                ip.extend_from_slice(&((20 + udp_length) as u16).to_be_bytes());
+                // no ID, don't fragment, a TTL of 64
+                ip.extend_from_slice(&[0, 0, 0x40, 0, 64, IPPROTO_UDP, 0, 0]);
+                ip.extend_from_slice(&from.octets());
+                ip.extend_from_slice(&to.octets());
+                let sum = checksum(&ip);
+                ip[10..12].copy_from_slice(&sum.to_be_bytes());
+
+                let mut pseudo_header = ip[12..20].to_vec();
+                pseudo_header.extend_from_slice(&[0, IPPROTO_UDP]);
+                pseudo_header.extend_from_slice(&(udp_length as u16).to_be_bytes());
+                (ip, pseudo_header)
+            }
+            (IpAddr::V6(from), IpAddr::V6(to)) => {
+                let mut ip = vec![0x60, 0, 0, 0];
+                ip.extend_from_slice(&(udp_length as u16).to_be_bytes());
+                ip.extend_from_slice(&[IPPROTO_UDP, 64]);
+                ip.extend_from_slice(&from.octets());
+                ip.extend_from_slice(&to.octets());
+
+                let mut pseudo_header = ip[8..40].to_vec();
+                pseudo_header.extend_from_slice(&(udp_length as u32).to_be_bytes());
+                pseudo_header.extend_from_slice(&[0, 0, 0, IPPROTO_UDP]);
+                (ip, pseudo_header)
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "source and destination are of different address families",
+                ))
+            }
+        };
+        pseudo_header.extend_from_slice(&udp);
+        // a checksum that comes to zero is sent as all ones
+        let sum = match checksum(&pseudo_header) {
+            0 => 0xffff,
+            sum => sum,
+        };
+        udp[6..8].copy_from_slice(&sum.to_be_bytes());
+        packet.extend_from_slice(&udp);
+
+        let since_epoch = when.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut record = (since_epoch.as_secs() as u32).to_le_bytes().to_vec();
+        record.extend_from_slice(&since_epoch.subsec_micros().to_le_bytes());
+        record.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        record.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        record.extend_from_slice(&packet);
+        self.out.write_all(&record)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// The address this host would send from to reach `server`; the port is
+/// whatever the system hands out.
+fn local_address(server: SocketAddr) -> SocketAddr {
+    let unspecified = match server {
+        SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+        SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+    };
+    // connecting a UDP socket sends nothing, it only picks a route
+    UdpSocket::bind(unspecified)
+        .and_then(|socket| socket.connect(server).and_then(|_| socket.local_addr()))
+        .unwrap_or(unspecified)
+}
+
+/// Writes each query and its reply to a pcap file as UDP packets.
+pub struct PcapRecorder {
+    writer: Mutex<PcapWriter<File>>,
+}
+
+impl PcapRecorder {
+    pub fn create(path: &str) -> io::Result<PcapRecorder> {
+        Ok(PcapRecorder {
+            writer: Mutex::new(PcapWriter::new(File::create(path)?)?),
+        })
+    }
+}
+
+impl Record for PcapRecorder {
+    /// The reply is written as arriving when the query was sent plus the
+    /// time it took.  A server given by name is written with the first of
+    /// its addresses.
+    fn record(&self, query: &Message, response: &Response) -> io::Result<()> {
+        let server = response
+            .server
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| invalid(&format!("no address for {}", response.server)))?;
+        let client = local_address(server);
+        let mut query = query.clone();
+        query.id = response.message.id;
+
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_udp(response.when, client, server, &query.to_wire())?;
+        writer.write_udp(
+            response.when + response.elapsed,
+            server,
+            client,
+            &response.wire,
+        )?;
+        writer.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query() -> Message {
+        crate::new_query("example.com".to_string(), "A".to_string())
+    }
+
+    #[test]
+    fn pcap_write_read() {
+        let when = UNIX_EPOCH + Duration::from_micros(1_582_502_400_250_000);
+        let client: SocketAddr = "192.0.2.1:49152".parse().unwrap();
+        let server: SocketAddr = "[2001:db8::53]:53".parse().unwrap();
+        let client6: SocketAddr = "[2001:db8::1]:49152".parse().unwrap();
+        let wire = query().to_wire();
+
+        let mut writer = PcapWriter::new(Vec::new()).unwrap();
+        writer
+            .write_udp(when, client, "192.0.2.53:53".parse().unwrap(), &wire)
+            .unwrap();
+        writer.write_udp(when, server, client6, &wire).unwrap();
+        assert!(writer.write_udp(when, client, server, &wire).is_err());
+        let file = writer.into_inner();
+        // IPv4 header checksum
+        assert_eq!(file[40 + 10..40 + 12], [0xb6, 0x7d]);
+
+        let captured = read(&file).unwrap();
+        assert_eq!(captured.len(), 2);
+        assert_eq!(captured[0].when, when);
+        assert_eq!(captured[0].source, client);
+        assert_eq!(captured[0].transport, Transport::Udp);
+        assert_eq!(
+            captured[0].message().unwrap(),
+            Message::from_wire(&wire).unwrap()
+        );
+        assert_eq!(captured[1].source, server);
+        assert_eq!(captured[1].destination, client6);
+        assert_eq!(captured[1].wire, wire);
+    }
+
+    #[test]
+    fn pcap_recorder_server_name() {
+        let path = std::env::temp_dir().join(format!("lud-pcap-{}.pcap", std::process::id()));
+        let path = path.to_str().unwrap();
+        let recorder = PcapRecorder::create(path).unwrap();
+        let wire = query().to_wire();
+        let when = UNIX_EPOCH + Duration::from_secs(1_582_502_400);
+        let elapsed = Duration::from_millis(12);
+        let server = "localhost:53".to_string();
+        let response = Response::new(&wire, server, Transport::Udp, when, elapsed).unwrap();
+        recorder.record(&query(), &response).unwrap();
+
+        let captured = read_file(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(captured.len(), 2);
+        assert!(captured[0].destination.ip().is_loopback());
+        assert_eq!(captured[1].source.port(), 53);
+        assert_eq!(captured[1].when, when + elapsed);
+    }
+
+    /// An Ethernet frame with an 802.1Q tag holding an IPv4 TCP segment.
+    fn tcp_frame(seq: u32, flags: u8, data: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&[0x81, 0x00, 0, 1, 0x08, 0x00]);
+        let mut ip = vec![0x45, 0];
+        ip.extend_from_slice(&((40 + data.len()) as u16).to_be_bytes());
+        ip.extend_from_slice(&[
+            0,
+            0,
+            0x40,
+            0,
+            64,
+            IPPROTO_TCP,
+            0,
+            0,
+            192,
+            0,
+            2,
+            1,
+            192,
+            0,
+            2,
+            53,
+        ]);
+        frame.extend_from_slice(&ip);
+        frame.extend_from_slice(&[0xc0, 0x00, 0, 53]);
+        frame.extend_from_slice(&seq.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 0, 0x50, flags, 0xff, 0xff, 0, 0, 0, 0]);
+        frame.extend_from_slice(data);
+        // Ethernet pads short frames
+        frame.extend_from_slice(&[0; 4]);
+        frame
+    }
+
+    fn pcapng_block(block_type: u32, body: &[u8]) -> Vec<u8> {
+        let length = 12 + body.len().div_ceil(4) * 4;
+        let mut block = block_type.to_le_bytes().to_vec();
+        block.extend_from_slice(&(length as u32).to_le_bytes());
+        block.extend_from_slice(body);
+        block.resize(length - 4, 0);
+        block.extend_from_slice(&(length as u32).to_le_bytes());
+        block
+    }
+
+    fn enhanced_packet(ticks: u64, frame: &[u8]) -> Vec<u8> {
+        let mut body = 0u32.to_le_bytes().to_vec();
+        body.extend_from_slice(&((ticks >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(ticks as u32).to_le_bytes());
+        body.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        body.extend_from_slice(frame);
+        pcapng_block(6, &body)
+    }
+
+    #[test]
+    fn pcapng_tcp_reassembly() {
+        // two messages over TCP, each preceded by its length
+        let wire = query().to_wire();
+        let mut stream = Vec::new();
+        for _ in 0..2 {
+            stream.extend_from_slice(&(wire.len() as u16).to_be_bytes());
+            stream.extend_from_slice(&wire);
+        }
+        let isn = u32::MAX - 4;
+        let first = isn.wrapping_add(1);
+
+        let mut file = Vec::new();
+        let mut section = PCAPNG_BYTE_ORDER.to_le_bytes().to_vec();
+        section.extend_from_slice(&[1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        file.extend(pcapng_block(PCAPNG_SECTION, &section));
+        // Ethernet, with timestamps in nanoseconds
+        file.extend(pcapng_block(
+            1,
+            &[1, 0, 0, 0, 0, 0, 0, 0, 9, 0, 1, 0, 9, 0, 0, 0, 0, 0, 0, 0],
+        ));
+        let segments = [
+            (0, isn, 0x02, &[][..]),
+            (1, first.wrapping_add(30), 0x18, &stream[30..]),
+            (2, first, 0x18, &stream[..20]),
+            // a retransmission overlapping what is already in order
+            (3, first.wrapping_add(10), 0x18, &stream[10..30]),
+        ];
+        for &(n, seq, flags, data) in segments.iter() {
+            let ticks = 1_582_502_400_000_000_000 + n;
+            file.extend(enhanced_packet(ticks, &tcp_frame(seq, flags, data)));
+        }
+
+        let captured = read(&file).unwrap();
+        assert_eq!(captured.len(), 2);
+        for message in captured.iter() {
+            assert_eq!(message.transport, Transport::Tcp);
+            assert_eq!(message.destination, "192.0.2.53:53".parse().unwrap());
+            assert_eq!(message.wire, wire);
+        }
+        // completed by the retransmission
+        assert_eq!(
+            captured[0].when,
+            UNIX_EPOCH + Duration::from_nanos(1_582_502_400_000_000_003)
+        );
+        assert!(read(b"not a capture at all").is_err());
+    }
+}
@@ -1,10 +1,10 @@
 use crate::rr::RRType;
-use crate::utils::{byte_combine, extract_name};
+use crate::utils::{byte_combine, extract_name, TRUNCATED};
 use std::convert::{AsRef, TryFrom};
 use std::error;
 use std::fmt;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u16)]
 pub enum QType {
     RRType(RRType),
@@ -17,7 +17,7 @@ pub enum QType {
 impl fmt::Display for QType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            QType::RRType(rrt) => write!(f, "{:?}", rrt),
+            QType::RRType(rrt) => write!(f, "{}", rrt),
             _ => write!(f, "{:?}", self),
         }
     }
@@ -44,7 +44,7 @@ impl error::Error for ParseError {
 impl From<QType> for u16 {
     fn from(original: QType) -> u16 {
         match original {
-            QType::RRType(rrtype) => rrtype as u16,
+            QType::RRType(rrtype) => u16::from(rrtype),
             QType::AXFR => 252,
             QType::MAILB => 253,
             QType::MAILA => 254,
@@ -73,10 +73,7 @@ impl TryFrom<u16> for QType {
     type Error = ParseError;
     fn try_from(original: u16) -> Result<Self, Self::Error> {
         match original {
-            1..=251 => match RRType::try_from(original) {
-                Ok(rrt) => Ok(QType::RRType(rrt)),
-                Err(_) => Err(ParseError),
-            },
+            1..=251 | 256..=65535 => Ok(QType::RRType(RRType::from(original))),
             252 => Ok(QType::AXFR),
             253 => Ok(QType::MAILB),
             254 => Ok(QType::MAILA),
@@ -86,6 +83,7 @@ impl TryFrom<u16> for QType {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Question {
     pub qname: Vec<String>,
     pub qtype: QType,
@@ -99,7 +97,11 @@ impl Question {
     ) -> Result<(Question, usize), &dyn error::Error> {
         let (qname, l_offset) = extract_name(wire, offset)?;
         offset = l_offset;
-        let qtype = QType::try_from(byte_combine(wire[offset], wire[offset + 1])).unwrap();
+        if wire.len() < offset + 4 {
+            return Err(TRUNCATED);
+        }
+        let qtype = QType::try_from(byte_combine(wire[offset], wire[offset + 1]))
+            .map_err(|_| &ParseError as &dyn error::Error)?;
         offset += 2;
         let qclass = byte_combine(wire[offset], wire[offset + 1]);
         offset += 2;
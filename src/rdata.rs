@@ -1,8 +1,38 @@
+use crate::compress::{Compression, NameCompressor};
+use crate::errors::ParseError;
 use crate::rr::RRType;
-use crate::utils::extract_name;
+use crate::ttl::Ttl;
+use crate::utils::{
+    base64_decode, base64_encode, extract_character_string, extract_name, fmt_character_string,
+    fqdn, hex_decode, hex_encode, name_to_wire, parse_presentation_name, unescape, TRUNCATED,
+};
+use rand::Rng;
 use std::error;
+use std::net::{Ipv4Addr, Ipv6Addr};
 use std::{fmt, str};
 
+fn single(tokens: &[String]) -> Result<&str, ParseError> {
+    match tokens {
+        [token] => Ok(token),
+        _ => Err(ParseError::InvalidRData),
+    }
+}
+
+fn parse_number<T: str::FromStr>(token: &str) -> Result<T, ParseError> {
+    token.parse().map_err(|_| ParseError::InvalidRData)
+}
+
+/// Decode the RFC 3597 generic notation: `\# <length> <hex>...`
+fn parse_generic(tokens: &[String]) -> Result<Vec<u8>, ParseError> {
+    let length: usize = parse_number(&tokens[1])?;
+    let data = hex_decode(&tokens[2..].concat())?;
+    if data.len() != length {
+        return Err(ParseError::InvalidRData);
+    }
+    Ok(data)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AData {
     address: [u8; 4],
 }
@@ -19,6 +49,23 @@ impl AData {
             ],
         }
     }
+
+    pub fn from_presentation(tokens: &[String]) -> Result<AData, ParseError> {
+        let address: Ipv4Addr = single(tokens)?
+            .parse()
+            .map_err(|_| ParseError::InvalidRData)?;
+        Ok(AData {
+            address: address.octets(),
+        })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        self.address.to_vec()
+    }
+
+    pub fn address(&self) -> Ipv4Addr {
+        Ipv4Addr::from(self.address)
+    }
 }
 
 impl fmt::Display for AData {
@@ -35,6 +82,7 @@ impl fmt::Display for AData {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct NSData {
     nsdname: Vec<String>,
 }
@@ -44,6 +92,15 @@ impl NSData {
         let (nsdname, _) = extract_name(buf, offset)?;
         Ok(NSData { nsdname: nsdname })
     }
+
+    pub fn from_presentation(tokens: &[String], origin: &[String]) -> Result<NSData, ParseError> {
+        let nsdname = parse_presentation_name(single(tokens)?, origin)?;
+        Ok(NSData { nsdname: nsdname })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        name_to_wire(&self.nsdname)
+    }
 }
 
 impl fmt::Display for NSData {
@@ -52,6 +109,7 @@ impl fmt::Display for NSData {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct CNAMEData {
     cname: Vec<String>,
 }
@@ -61,6 +119,22 @@ impl CNAMEData {
         let (cname, _) = extract_name(buf, offset)?;
         Ok(CNAMEData { cname: cname })
     }
+
+    pub fn from_presentation(
+        tokens: &[String],
+        origin: &[String],
+    ) -> Result<CNAMEData, ParseError> {
+        let cname = parse_presentation_name(single(tokens)?, origin)?;
+        Ok(CNAMEData { cname: cname })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        name_to_wire(&self.cname)
+    }
+
+    pub fn cname(&self) -> &[String] {
+        &self.cname
+    }
 }
 
 impl fmt::Display for CNAMEData {
@@ -69,6 +143,7 @@ impl fmt::Display for CNAMEData {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SOAData {
     mname: Vec<String>,
     rname: Vec<String>,
@@ -76,12 +151,16 @@ pub struct SOAData {
     refresh: u32,
     retry: u32,
     expire: u32,
+    minimum: u32,
 }
 
 impl SOAData {
     pub fn from_wire(buf: &[u8], offset: usize) -> Result<SOAData, &dyn error::Error> {
         let (mname, offset) = extract_name(buf, offset)?;
         let (rname, offset) = extract_name(buf, offset)?;
+        if offset + 20 > buf.len() {
+            return Err(&ParseError::InvalidRData);
+        }
         let serial = (buf[offset] as u32) << 24
             | (buf[offset + 1] as u32) << 16
             | (buf[offset + 2] as u32) << 8
@@ -101,6 +180,11 @@ impl SOAData {
             | (buf[offset + 1] as u32) << 16
             | (buf[offset + 2] as u32) << 8
             | (buf[offset + 3] as u32);
+        let offset = offset + 4;
+        let minimum = (buf[offset] as u32) << 24
+            | (buf[offset + 1] as u32) << 16
+            | (buf[offset + 2] as u32) << 8
+            | (buf[offset + 3] as u32);
         Ok(SOAData {
             mname: mname,
             rname: rname,
@@ -108,25 +192,89 @@ impl SOAData {
             refresh: refresh,
             retry: retry,
             expire: expire,
+            minimum: minimum,
         })
     }
+
+    pub fn from_presentation(tokens: &[String], origin: &[String]) -> Result<SOAData, ParseError> {
+        if tokens.len() != 7 {
+            return Err(ParseError::InvalidRData);
+        }
+        Ok(SOAData {
+            mname: parse_presentation_name(&tokens[0], origin)?,
+            rname: parse_presentation_name(&tokens[1], origin)?,
+            serial: parse_number(&tokens[2])?,
+            refresh: parse_number(&tokens[3])?,
+            retry: parse_number(&tokens[4])?,
+            expire: parse_number(&tokens[5])?,
+            minimum: parse_number(&tokens[6])?,
+        })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = name_to_wire(&self.mname);
+        wire.extend(name_to_wire(&self.rname));
+        for field in [
+            self.serial,
+            self.refresh,
+            self.retry,
+            self.expire,
+            self.minimum,
+        ]
+        .iter()
+        {
+            wire.extend_from_slice(&field.to_be_bytes());
+        }
+        wire
+    }
+
+    pub fn mname(&self) -> &[String] {
+        &self.mname
+    }
+
+    pub fn rname(&self) -> &[String] {
+        &self.rname
+    }
+
+    pub fn serial(&self) -> u32 {
+        self.serial
+    }
+
+    pub fn refresh(&self) -> Ttl {
+        Ttl::from_secs(self.refresh)
+    }
+
+    pub fn retry(&self) -> Ttl {
+        Ttl::from_secs(self.retry)
+    }
+
+    pub fn expire(&self) -> Ttl {
+        Ttl::from_secs(self.expire)
+    }
+
+    /// How long a negative answer from the zone may be cached (RFC 2308 §4).
+    pub fn minimum(&self) -> Ttl {
+        Ttl::from_secs(self.minimum)
+    }
 }
 
 impl fmt::Display for SOAData {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "{}\t{}\t{}\t{}\t{}\t{}",
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
             self.mname.join("."),
             self.rname.join("."),
             self.serial,
             self.refresh,
             self.retry,
-            self.expire
+            self.expire,
+            self.minimum
         )
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct MXData {
     preference: u16,
     exchange: Vec<String>,
@@ -141,6 +289,30 @@ impl MXData {
             exchange: exchange,
         })
     }
+
+    pub fn from_presentation(tokens: &[String], origin: &[String]) -> Result<MXData, ParseError> {
+        if tokens.len() != 2 {
+            return Err(ParseError::InvalidRData);
+        }
+        Ok(MXData {
+            preference: parse_number(&tokens[0])?,
+            exchange: parse_presentation_name(&tokens[1], origin)?,
+        })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = self.preference.to_be_bytes().to_vec();
+        wire.extend(name_to_wire(&self.exchange));
+        wire
+    }
+
+    pub fn preference(&self) -> u16 {
+        self.preference
+    }
+
+    pub fn exchange(&self) -> &[String] {
+        &self.exchange
+    }
 }
 
 impl fmt::Display for MXData {
@@ -149,6 +321,7 @@ impl fmt::Display for MXData {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct PTRData {
     ptrdname: Vec<String>,
 }
@@ -158,6 +331,19 @@ impl PTRData {
         let (ptrdname, _) = extract_name(buf, offset)?;
         Ok(PTRData { ptrdname: ptrdname })
     }
+
+    pub fn from_presentation(tokens: &[String], origin: &[String]) -> Result<PTRData, ParseError> {
+        let ptrdname = parse_presentation_name(single(tokens)?, origin)?;
+        Ok(PTRData { ptrdname: ptrdname })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        name_to_wire(&self.ptrdname)
+    }
+
+    pub fn ptrdname(&self) -> &[String] {
+        &self.ptrdname
+    }
 }
 
 impl fmt::Display for PTRData {
@@ -166,25 +352,81 @@ impl fmt::Display for PTRData {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TXTData {
-    txtdata: Vec<u8>, // Should actually be a Vec of <character-string> (cf RFC1035)
+    strings: Vec<Vec<u8>>, // one or more <character-string>s (RFC 1035 §3.3.14)
 }
 
 impl TXTData {
-    pub fn from_wire(buf: &[u8], offset: usize, rdlength: usize) -> TXTData {
-        TXTData {
-            txtdata: buf[offset..offset + rdlength].to_owned(),
+    pub fn from_wire(
+        buf: &[u8],
+        offset: usize,
+        rdlength: usize,
+    ) -> Result<TXTData, &dyn error::Error> {
+        let end = offset + rdlength;
+        let mut strings = Vec::new();
+        let mut offset = offset;
+        while offset < end {
+            if offset + 1 + buf[offset] as usize > end {
+                return Err(&ParseError::InvalidRData);
+            }
+            let (string, next) = extract_character_string(buf, offset)?;
+            strings.push(string);
+            offset = next;
+        }
+        Ok(TXTData { strings: strings })
+    }
+
+    pub fn from_presentation(tokens: &[String]) -> Result<TXTData, ParseError> {
+        let mut strings = Vec::new();
+        for token in tokens.iter() {
+            let string = unescape(token)?;
+            if string.len() > 255 {
+                return Err(ParseError::InvalidRData);
+            }
+            strings.push(string);
+        }
+        Ok(TXTData { strings: strings })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = Vec::new();
+        for string in self.strings.iter() {
+            wire.push(string.len() as u8);
+            wire.extend(string.iter());
         }
+        wire
+    }
+
+    pub fn strings(&self) -> &[Vec<u8>] {
+        &self.strings
+    }
+
+    /// The character-strings concatenated without separators, which is how
+    /// SPF (RFC 7208 §3.3) and DKIM (RFC 6376 §3.6.2.2) records longer than
+    /// 255 octets are reassembled.
+    pub fn joined(&self) -> Vec<u8> {
+        self.strings.concat()
+    }
+
+    /// As `joined`, decoded as text with invalid UTF-8 replaced.
+    pub fn joined_text(&self) -> String {
+        String::from_utf8_lossy(&self.joined()).into_owned()
     }
 }
 
 impl fmt::Display for TXTData {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // XXX: TXT is not necessarily utf8, read the specs and fix
-        write!(f, "{:?}", str::from_utf8(&self.txtdata).unwrap())
+        let strings: Vec<String> = self
+            .strings
+            .iter()
+            .map(|s| fmt_character_string(s))
+            .collect();
+        write!(f, "{}", strings.join(" "))
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AAAAData {
     address: [u8; 16],
 }
@@ -198,6 +440,23 @@ impl AAAAData {
 
         AAAAData { address: addr }
     }
+
+    pub fn from_presentation(tokens: &[String]) -> Result<AAAAData, ParseError> {
+        let address: Ipv6Addr = single(tokens)?
+            .parse()
+            .map_err(|_| ParseError::InvalidRData)?;
+        Ok(AAAAData {
+            address: address.octets(),
+        })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        self.address.to_vec()
+    }
+
+    pub fn address(&self) -> Ipv6Addr {
+        Ipv6Addr::from(self.address)
+    }
 }
 
 impl fmt::Display for AAAAData {
@@ -269,66 +528,1723 @@ impl fmt::Display for AAAAData {
     }
 }
 
-pub enum RData {
-    A(AData),
-    NS(NSData),
-    CNAME(CNAMEData),
-    SOA(SOAData),
-    PTR(PTRData),
-    MX(MXData),
-    TXT(TXTData),
-    AAAA(AAAAData),
-    UNKNOWN(u16),
+// MD and MF are obsolete (RFC 973), superseded by MX
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MDData {
+    madname: Vec<String>,
 }
 
-impl fmt::Display for RData {
+impl MDData {
+    pub fn from_wire(buf: &[u8], offset: usize) -> Result<MDData, &dyn error::Error> {
+        let (madname, _) = extract_name(buf, offset)?;
+        Ok(MDData { madname })
+    }
+
+    pub fn from_presentation(tokens: &[String], origin: &[String]) -> Result<MDData, ParseError> {
+        let madname = parse_presentation_name(single(tokens)?, origin)?;
+        Ok(MDData { madname })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        name_to_wire(&self.madname)
+    }
+}
+
+impl fmt::Display for MDData {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            RData::A(a_data) => a_data.fmt(f),
-            RData::NS(ns_data) => ns_data.fmt(f),
-            RData::CNAME(cname_data) => cname_data.fmt(f),
-            RData::SOA(soa_data) => soa_data.fmt(f),
-            RData::PTR(ptr_data) => ptr_data.fmt(f),
-            RData::MX(mx_data) => mx_data.fmt(f),
-            RData::TXT(txt_data) => txt_data.fmt(f),
-            RData::AAAA(aaaa_data) => aaaa_data.fmt(f),
-            RData::UNKNOWN(rrtype) => write!(f, "{}", rrtype),
+        write!(f, "{}", self.madname.join("."))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MFData {
+    madname: Vec<String>,
+}
+
+impl MFData {
+    pub fn from_wire(buf: &[u8], offset: usize) -> Result<MFData, &dyn error::Error> {
+        let (madname, _) = extract_name(buf, offset)?;
+        Ok(MFData { madname })
+    }
+
+    pub fn from_presentation(tokens: &[String], origin: &[String]) -> Result<MFData, ParseError> {
+        let madname = parse_presentation_name(single(tokens)?, origin)?;
+        Ok(MFData { madname })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        name_to_wire(&self.madname)
+    }
+}
+
+impl fmt::Display for MFData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.madname.join("."))
+    }
+}
+
+// MB, MG and MR are the experimental mailbox types of RFC 1035 §3.3
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MBData {
+    madname: Vec<String>,
+}
+
+impl MBData {
+    pub fn from_wire(buf: &[u8], offset: usize) -> Result<MBData, &dyn error::Error> {
+        let (madname, _) = extract_name(buf, offset)?;
+        Ok(MBData { madname })
+    }
+
+    pub fn from_presentation(tokens: &[String], origin: &[String]) -> Result<MBData, ParseError> {
+        let madname = parse_presentation_name(single(tokens)?, origin)?;
+        Ok(MBData { madname })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        name_to_wire(&self.madname)
+    }
+}
+
+impl fmt::Display for MBData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.madname.join("."))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MGData {
+    mgmname: Vec<String>,
+}
+
+impl MGData {
+    pub fn from_wire(buf: &[u8], offset: usize) -> Result<MGData, &dyn error::Error> {
+        let (mgmname, _) = extract_name(buf, offset)?;
+        Ok(MGData { mgmname })
+    }
+
+    pub fn from_presentation(tokens: &[String], origin: &[String]) -> Result<MGData, ParseError> {
+        let mgmname = parse_presentation_name(single(tokens)?, origin)?;
+        Ok(MGData { mgmname })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        name_to_wire(&self.mgmname)
+    }
+}
+
+impl fmt::Display for MGData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.mgmname.join("."))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MRData {
+    newname: Vec<String>,
+}
+
+impl MRData {
+    pub fn from_wire(buf: &[u8], offset: usize) -> Result<MRData, &dyn error::Error> {
+        let (newname, _) = extract_name(buf, offset)?;
+        Ok(MRData { newname })
+    }
+
+    pub fn from_presentation(tokens: &[String], origin: &[String]) -> Result<MRData, ParseError> {
+        let newname = parse_presentation_name(single(tokens)?, origin)?;
+        Ok(MRData { newname })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        name_to_wire(&self.newname)
+    }
+}
+
+impl fmt::Display for MRData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.newname.join("."))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NULLData {
+    anything: Vec<u8>,
+}
+
+impl NULLData {
+    pub fn from_wire(buf: &[u8], offset: usize, rdlength: usize) -> NULLData {
+        NULLData {
+            anything: buf[offset..offset + rdlength].to_owned(),
         }
     }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        self.anything.clone()
+    }
 }
 
-impl RData {
-    pub fn from_wire(
-        rrtype: RRType,
-        buf: &[u8],
-        offset: usize,
-        rdlength: usize,
-    ) -> Result<RData, &dyn error::Error> {
-        match rrtype {
-            RRType::A => Ok(RData::A(AData::from_wire(buf, offset))),
-            RRType::NS => {
-                let ns = NSData::from_wire(buf, offset)?;
-                Ok(RData::NS(ns))
-            }
-            RRType::CNAME => {
-                let cname = CNAMEData::from_wire(buf, offset)?;
-                Ok(RData::CNAME(cname))
-            }
-            RRType::SOA => {
-                let soa = SOAData::from_wire(buf, offset)?;
-                Ok(RData::SOA(soa))
-            }
-            RRType::PTR => {
-                let ptr = PTRData::from_wire(buf, offset)?;
-                Ok(RData::PTR(ptr))
+/// RDATA in the RFC 3597 generic notation, e.g. `\\# 2 beef`.
+fn fmt_generic(data: &[u8]) -> String {
+    let mut fmt_str = format!("\\# {}", data.len());
+    if !data.is_empty() {
+        fmt_str.push(' ');
+        for byte in data.iter() {
+            fmt_str.push_str(&format!("{:02x}", byte));
+        }
+    }
+    fmt_str
+}
+
+impl fmt::Display for NULLData {
+    // NULL has no presentation format, so use the RFC 3597 generic one
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", fmt_generic(&self.anything))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WKSData {
+    address: [u8; 4],
+    protocol: u8,
+    bitmap: Vec<u8>,
+}
+
+impl WKSData {
+    pub fn from_wire(buf: &[u8], offset: usize, rdlength: usize) -> WKSData {
+        WKSData {
+            address: [buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]],
+            protocol: buf[offset + 4],
+            bitmap: buf[offset + 5..offset + rdlength].to_owned(),
+        }
+    }
+
+    pub fn from_presentation(tokens: &[String]) -> Result<WKSData, ParseError> {
+        if tokens.len() < 2 {
+            return Err(ParseError::InvalidRData);
+        }
+        let address: Ipv4Addr = parse_number(&tokens[0])?;
+        let protocol = match tokens[1].to_lowercase().as_ref() {
+            "tcp" => 6,
+            "udp" => 17,
+            number => parse_number(number)?,
+        };
+        let mut bitmap = Vec::new();
+        for token in tokens[2..].iter() {
+            let port: u16 = parse_number(token)?;
+            let byte = port as usize / 8;
+            if bitmap.len() <= byte {
+                bitmap.resize(byte + 1, 0);
             }
-            RRType::MX => {
-                let mx = MXData::from_wire(buf, offset)?;
-                Ok(RData::MX(mx))
+            bitmap[byte] |= 0x80 >> (port % 8);
+        }
+        Ok(WKSData {
+            address: address.octets(),
+            protocol,
+            bitmap,
+        })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = self.address.to_vec();
+        wire.push(self.protocol);
+        wire.extend(self.bitmap.iter());
+        wire
+    }
+
+    /// The ports whose bits are set in the bitmap; bit 0 is port 0.
+    pub fn ports(&self) -> Vec<u16> {
+        let mut ports = Vec::new();
+        for (i, byte) in self.bitmap.iter().enumerate() {
+            for bit in 0..8 {
+                if byte & (0x80 >> bit) != 0 {
+                    ports.push((i * 8 + bit) as u16);
+                }
             }
-            RRType::TXT => Ok(RData::TXT(TXTData::from_wire(buf, offset, rdlength))),
-            RRType::AAAA => Ok(RData::AAAA(AAAAData::from_wire(buf, offset))),
-            _ => Ok(RData::UNKNOWN(rrtype as u16)),
         }
+        ports
+    }
+}
+
+impl fmt::Display for WKSData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let protocol = match self.protocol {
+            6 => "tcp".to_string(),
+            17 => "udp".to_string(),
+            other => other.to_string(),
+        };
+        let mut fmt_str = format!("{}\t{}", Ipv4Addr::from(self.address), protocol);
+        for port in self.ports() {
+            fmt_str.push_str(&format!(" {}", port));
+        }
+        write!(f, "{}", fmt_str)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HINFOData {
+    cpu: Vec<u8>,
+    os: Vec<u8>,
+}
+
+impl HINFOData {
+    pub fn from_wire(buf: &[u8], offset: usize) -> Result<HINFOData, &dyn error::Error> {
+        let (cpu, offset) = extract_character_string(buf, offset)?;
+        let (os, _) = extract_character_string(buf, offset)?;
+        Ok(HINFOData { cpu, os })
+    }
+
+    pub fn from_presentation(tokens: &[String]) -> Result<HINFOData, ParseError> {
+        if tokens.len() != 2 {
+            return Err(ParseError::InvalidRData);
+        }
+        let cpu = unescape(&tokens[0])?;
+        let os = unescape(&tokens[1])?;
+        if cpu.len() > 255 || os.len() > 255 {
+            return Err(ParseError::InvalidRData);
+        }
+        Ok(HINFOData { cpu, os })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = vec![self.cpu.len() as u8];
+        wire.extend(self.cpu.iter());
+        wire.push(self.os.len() as u8);
+        wire.extend(self.os.iter());
+        wire
+    }
+}
+
+impl fmt::Display for HINFOData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}",
+            fmt_character_string(&self.cpu),
+            fmt_character_string(&self.os)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MINFOData {
+    rmailbx: Vec<String>,
+    emailbx: Vec<String>,
+}
+
+impl MINFOData {
+    pub fn from_wire(buf: &[u8], offset: usize) -> Result<MINFOData, &dyn error::Error> {
+        let (rmailbx, offset) = extract_name(buf, offset)?;
+        let (emailbx, _) = extract_name(buf, offset)?;
+        Ok(MINFOData {
+            rmailbx,
+            emailbx,
+        })
+    }
+
+    pub fn from_presentation(
+        tokens: &[String],
+        origin: &[String],
+    ) -> Result<MINFOData, ParseError> {
+        if tokens.len() != 2 {
+            return Err(ParseError::InvalidRData);
+        }
+        Ok(MINFOData {
+            rmailbx: parse_presentation_name(&tokens[0], origin)?,
+            emailbx: parse_presentation_name(&tokens[1], origin)?,
+        })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = name_to_wire(&self.rmailbx);
+        wire.extend(name_to_wire(&self.emailbx));
+        wire
+    }
+}
+
+impl fmt::Display for MINFOData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\t{}", self.rmailbx.join("."), self.emailbx.join("."))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SRVData {
+    priority: u16,
+    weight: u16,
+    port: u16,
+    target: Vec<String>,
+}
+
+impl SRVData {
+    pub fn from_wire(buf: &[u8], offset: usize) -> Result<SRVData, &dyn error::Error> {
+        let priority = (buf[offset] as u16) << 8 | (buf[offset + 1] as u16);
+        let weight = (buf[offset + 2] as u16) << 8 | (buf[offset + 3] as u16);
+        let port = (buf[offset + 4] as u16) << 8 | (buf[offset + 5] as u16);
+        let (target, _) = extract_name(buf, offset + 6)?;
+        Ok(SRVData {
+            priority,
+            weight,
+            port,
+            target,
+        })
+    }
+
+    pub fn from_presentation(tokens: &[String], origin: &[String]) -> Result<SRVData, ParseError> {
+        if tokens.len() != 4 {
+            return Err(ParseError::InvalidRData);
+        }
+        Ok(SRVData {
+            priority: parse_number(&tokens[0])?,
+            weight: parse_number(&tokens[1])?,
+            port: parse_number(&tokens[2])?,
+            target: parse_presentation_name(&tokens[3], origin)?,
+        })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = Vec::new();
+        wire.extend_from_slice(&self.priority.to_be_bytes());
+        wire.extend_from_slice(&self.weight.to_be_bytes());
+        wire.extend_from_slice(&self.port.to_be_bytes());
+        wire.extend(name_to_wire(&self.target));
+        wire
+    }
+
+    pub fn priority(&self) -> u16 {
+        self.priority
+    }
+
+    pub fn weight(&self) -> u16 {
+        self.weight
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// The target host; a target of "." (no labels) means the service is
+    /// decidedly not available at this domain.
+    pub fn target(&self) -> &[String] {
+        &self.target
+    }
+}
+
+impl fmt::Display for SRVData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}",
+            self.priority,
+            self.weight,
+            self.port,
+            self.target.join(".")
+        )
+    }
+}
+
+/// Order SRV targets for contacting them, per RFC 2782: lowest priority
+/// first, and within a priority by weighted random selection, so that each
+/// target is tried first with a probability proportional to its weight.
+pub fn order_srv_targets<'a, R: Rng>(records: &[&'a SRVData], rng: &mut R) -> Vec<&'a SRVData> {
+    let mut remaining: Vec<&SRVData> = records.to_vec();
+    remaining.sort_by_key(|srv| srv.priority);
+
+    let mut ordered = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        let priority = remaining[0].priority;
+        let split = remaining
+            .iter()
+            .position(|srv| srv.priority != priority)
+            .unwrap_or(remaining.len());
+        let mut group: Vec<&SRVData> = remaining.drain(..split).collect();
+        // zero weight entries go first, so that they only have a small
+        // chance of being selected early
+        group.sort_by_key(|srv| srv.weight != 0);
+
+        while !group.is_empty() {
+            let total: u32 = group.iter().map(|srv| srv.weight as u32).sum();
+            let pick = rng.gen_range(0, total + 1);
+            let mut running = 0;
+            let mut chosen = group.len() - 1;
+            for (i, srv) in group.iter().enumerate() {
+                running += srv.weight as u32;
+                if running >= pick {
+                    chosen = i;
+                    break;
+                }
+            }
+            ordered.push(group.remove(chosen));
+        }
+    }
+    ordered
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NAPTRData {
+    order: u16,
+    preference: u16,
+    flags: Vec<u8>,
+    services: Vec<u8>,
+    regexp: Vec<u8>,
+    replacement: Vec<String>,
+}
+
+impl NAPTRData {
+    pub fn from_wire(buf: &[u8], offset: usize) -> Result<NAPTRData, &dyn error::Error> {
+        let order = (buf[offset] as u16) << 8 | (buf[offset + 1] as u16);
+        let preference = (buf[offset + 2] as u16) << 8 | (buf[offset + 3] as u16);
+        let (flags, offset) = extract_character_string(buf, offset + 4)?;
+        let (services, offset) = extract_character_string(buf, offset)?;
+        let (regexp, offset) = extract_character_string(buf, offset)?;
+        let (replacement, _) = extract_name(buf, offset)?;
+        Ok(NAPTRData {
+            order,
+            preference,
+            flags,
+            services,
+            regexp,
+            replacement,
+        })
+    }
+
+    pub fn from_presentation(
+        tokens: &[String],
+        origin: &[String],
+    ) -> Result<NAPTRData, ParseError> {
+        if tokens.len() != 6 {
+            return Err(ParseError::InvalidRData);
+        }
+        Ok(NAPTRData {
+            order: parse_number(&tokens[0])?,
+            preference: parse_number(&tokens[1])?,
+            flags: unescape(&tokens[2])?,
+            services: unescape(&tokens[3])?,
+            regexp: unescape(&tokens[4])?,
+            replacement: parse_presentation_name(&tokens[5], origin)?,
+        })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = Vec::new();
+        wire.extend_from_slice(&self.order.to_be_bytes());
+        wire.extend_from_slice(&self.preference.to_be_bytes());
+        for string in [&self.flags, &self.services, &self.regexp].iter() {
+            wire.push(string.len() as u8);
+            wire.extend(string.iter());
+        }
+        wire.extend(name_to_wire(&self.replacement));
+        wire
+    }
+}
+
+impl fmt::Display for NAPTRData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.order,
+            self.preference,
+            fmt_character_string(&self.flags),
+            fmt_character_string(&self.services),
+            fmt_character_string(&self.regexp),
+            self.replacement.join(".")
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct URIData {
+    priority: u16,
+    weight: u16,
+    target: Vec<u8>,
+}
+
+impl URIData {
+    pub fn from_wire(buf: &[u8], offset: usize, rdlength: usize) -> URIData {
+        // the target is not a <character-string>, it runs to the end of RDATA
+        URIData {
+            priority: (buf[offset] as u16) << 8 | (buf[offset + 1] as u16),
+            weight: (buf[offset + 2] as u16) << 8 | (buf[offset + 3] as u16),
+            target: buf[offset + 4..offset + rdlength].to_owned(),
+        }
+    }
+
+    pub fn from_presentation(tokens: &[String]) -> Result<URIData, ParseError> {
+        if tokens.len() != 3 {
+            return Err(ParseError::InvalidRData);
+        }
+        Ok(URIData {
+            priority: parse_number(&tokens[0])?,
+            weight: parse_number(&tokens[1])?,
+            target: unescape(&tokens[2])?,
+        })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = Vec::new();
+        wire.extend_from_slice(&self.priority.to_be_bytes());
+        wire.extend_from_slice(&self.weight.to_be_bytes());
+        wire.extend(self.target.iter());
+        wire
+    }
+}
+
+impl fmt::Display for URIData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}",
+            self.priority,
+            self.weight,
+            fmt_character_string(&self.target)
+        )
+    }
+}
+
+/* Service binding parameters, the key=value pairs of SVCB and HTTPS
+ * records (RFC 9460 §7). */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SvcParam {
+    Mandatory(Vec<u16>),
+    Alpn(Vec<Vec<u8>>),
+    NoDefaultAlpn,
+    Port(u16),
+    Ipv4Hint(Vec<Ipv4Addr>),
+    Ech(Vec<u8>),
+    Ipv6Hint(Vec<Ipv6Addr>),
+    Unknown(u16, Vec<u8>),
+}
+
+fn svc_key_name(key: u16) -> String {
+    match key {
+        0 => "mandatory".to_string(),
+        1 => "alpn".to_string(),
+        2 => "no-default-alpn".to_string(),
+        3 => "port".to_string(),
+        4 => "ipv4hint".to_string(),
+        5 => "ech".to_string(),
+        6 => "ipv6hint".to_string(),
+        key => format!("key{}", key),
+    }
+}
+
+fn svc_key_from_name(name: &str) -> Result<u16, ParseError> {
+    match name {
+        "mandatory" => Ok(0),
+        "alpn" => Ok(1),
+        "no-default-alpn" => Ok(2),
+        "port" => Ok(3),
+        "ipv4hint" => Ok(4),
+        "ech" => Ok(5),
+        "ipv6hint" => Ok(6),
+        _ => match name.strip_prefix("key") {
+            Some(number) => parse_number(number),
+            None => Err(ParseError::InvalidRData),
+        },
+    }
+}
+
+// Split a value list on commas that are not escaped.
+fn split_value_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push(c);
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            ',' => items.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    items.push(current);
+    items
+}
+
+fn fmt_alpn_id(id: &[u8]) -> String {
+    let mut out = String::new();
+    for &byte in id.iter() {
+        match byte {
+            b',' | b'\\' | b'"' => {
+                out.push('\\');
+                out.push(byte as char);
+            }
+            0x21..=0x7e => out.push(byte as char),
+            _ => out.push_str(&format!("\\{:03}", byte)),
+        }
+    }
+    out
+}
+
+impl SvcParam {
+    pub fn key(&self) -> u16 {
+        match self {
+            SvcParam::Mandatory(_) => 0,
+            SvcParam::Alpn(_) => 1,
+            SvcParam::NoDefaultAlpn => 2,
+            SvcParam::Port(_) => 3,
+            SvcParam::Ipv4Hint(_) => 4,
+            SvcParam::Ech(_) => 5,
+            SvcParam::Ipv6Hint(_) => 6,
+            SvcParam::Unknown(key, _) => *key,
+        }
+    }
+
+    fn from_wire(key: u16, value: &[u8]) -> Result<SvcParam, ParseError> {
+        match key {
+            0 => {
+                if value.is_empty() || !value.len().is_multiple_of(2) {
+                    return Err(ParseError::InvalidRData);
+                }
+                let keys = value
+                    .chunks(2)
+                    .map(|k| (k[0] as u16) << 8 | k[1] as u16)
+                    .collect();
+                Ok(SvcParam::Mandatory(keys))
+            }
+            1 => {
+                let mut ids = Vec::new();
+                let mut offset = 0;
+                while offset < value.len() {
+                    let length = value[offset] as usize;
+                    if length == 0 || offset + 1 + length > value.len() {
+                        return Err(ParseError::InvalidRData);
+                    }
+                    ids.push(value[offset + 1..offset + 1 + length].to_owned());
+                    offset += 1 + length;
+                }
+                Ok(SvcParam::Alpn(ids))
+            }
+            2 if value.is_empty() => Ok(SvcParam::NoDefaultAlpn),
+            3 if value.len() == 2 => Ok(SvcParam::Port((value[0] as u16) << 8 | value[1] as u16)),
+            4 if !value.is_empty() && value.len().is_multiple_of(4) => Ok(SvcParam::Ipv4Hint(
+                value
+                    .chunks(4)
+                    .map(|a| Ipv4Addr::new(a[0], a[1], a[2], a[3]))
+                    .collect(),
+            )),
+            5 => Ok(SvcParam::Ech(value.to_owned())),
+            6 if !value.is_empty() && value.len().is_multiple_of(16) => Ok(SvcParam::Ipv6Hint(
+                value
+                    .chunks(16)
+                    .map(|a| {
+                        let mut octets = [0u8; 16];
+                        octets.copy_from_slice(a);
+                        Ipv6Addr::from(octets)
+                    })
+                    .collect(),
+            )),
+            2..=6 => Err(ParseError::InvalidRData),
+            _ => Ok(SvcParam::Unknown(key, value.to_owned())),
+        }
+    }
+
+    fn from_presentation(key: &str, value: Option<&str>) -> Result<SvcParam, ParseError> {
+        let key = svc_key_from_name(key)?;
+        let value = match (key, value) {
+            (2, None) => return Ok(SvcParam::NoDefaultAlpn),
+            (_, None) if key <= 6 => return Err(ParseError::InvalidRData),
+            (_, None) => return Ok(SvcParam::Unknown(key, Vec::new())),
+            (_, Some(value)) => value,
+        };
+        let items = split_value_list(value);
+        match key {
+            0 => {
+                let mut keys = items
+                    .iter()
+                    .map(|k| svc_key_from_name(k))
+                    .collect::<Result<Vec<u16>, _>>()?;
+                // sorted, without repeats and never naming itself (RFC 9460 §8)
+                keys.sort_unstable();
+                if keys.contains(&0) || keys.windows(2).any(|k| k[0] == k[1]) {
+                    return Err(ParseError::InvalidRData);
+                }
+                Ok(SvcParam::Mandatory(keys))
+            }
+            1 => {
+                let ids = items
+                    .iter()
+                    .map(|id| unescape(id))
+                    .collect::<Result<Vec<Vec<u8>>, _>>()?;
+                if ids.iter().any(|id| id.is_empty() || id.len() > 255) {
+                    return Err(ParseError::InvalidRData);
+                }
+                Ok(SvcParam::Alpn(ids))
+            }
+            3 => Ok(SvcParam::Port(parse_number(value)?)),
+            4 => Ok(SvcParam::Ipv4Hint(
+                items
+                    .iter()
+                    .map(|a| parse_number(a))
+                    .collect::<Result<_, _>>()?,
+            )),
+            5 => Ok(SvcParam::Ech(base64_decode(value)?)),
+            6 => Ok(SvcParam::Ipv6Hint(
+                items
+                    .iter()
+                    .map(|a| parse_number(a))
+                    .collect::<Result<_, _>>()?,
+            )),
+            2 => Err(ParseError::InvalidRData), // no-default-alpn takes no value
+            _ => Ok(SvcParam::Unknown(key, unescape(value)?)),
+        }
+    }
+
+    fn value_to_wire(&self) -> Vec<u8> {
+        match self {
+            SvcParam::Mandatory(keys) => keys.iter().flat_map(|k| k.to_be_bytes()).collect(),
+            SvcParam::Alpn(ids) => {
+                let mut wire = Vec::new();
+                for id in ids.iter() {
+                    wire.push(id.len() as u8);
+                    wire.extend(id.iter());
+                }
+                wire
+            }
+            SvcParam::NoDefaultAlpn => Vec::new(),
+            SvcParam::Port(port) => port.to_be_bytes().to_vec(),
+            SvcParam::Ipv4Hint(addrs) => addrs.iter().flat_map(|a| a.octets()).collect(),
+            SvcParam::Ech(config) => config.clone(),
+            SvcParam::Ipv6Hint(addrs) => addrs.iter().flat_map(|a| a.octets()).collect(),
+            SvcParam::Unknown(_, value) => value.clone(),
+        }
+    }
+}
+
+impl fmt::Display for SvcParam {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = svc_key_name(self.key());
+        let value = match self {
+            SvcParam::Mandatory(keys) => keys
+                .iter()
+                .map(|k| svc_key_name(*k))
+                .collect::<Vec<_>>()
+                .join(","),
+            SvcParam::Alpn(ids) => ids
+                .iter()
+                .map(|id| fmt_alpn_id(id))
+                .collect::<Vec<_>>()
+                .join(","),
+            SvcParam::NoDefaultAlpn => return write!(f, "{}", name),
+            SvcParam::Port(port) => port.to_string(),
+            SvcParam::Ipv4Hint(addrs) => addrs
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            SvcParam::Ech(config) => base64_encode(config),
+            SvcParam::Ipv6Hint(addrs) => addrs
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            SvcParam::Unknown(_, value) => fmt_character_string(value),
+        };
+        write!(f, "{}={}", name, value)
+    }
+}
+
+/* SVCB (type 64) and HTTPS (type 65) share a wire and presentation format;
+ * a priority of 0 marks AliasMode, anything else ServiceMode. */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SVCBData {
+    priority: u16,
+    target: Vec<String>,
+    params: Vec<SvcParam>,
+}
+
+impl SVCBData {
+    pub fn from_wire(
+        buf: &[u8],
+        offset: usize,
+        rdlength: usize,
+    ) -> Result<SVCBData, &dyn error::Error> {
+        let end = offset + rdlength;
+        let priority = (buf[offset] as u16) << 8 | (buf[offset + 1] as u16);
+        // the target name is never compressed
+        let (target, mut offset) = extract_name(buf, offset + 2)?;
+
+        let mut params: Vec<SvcParam> = Vec::new();
+        while offset < end {
+            if offset + 4 > end {
+                return Err(&ParseError::InvalidRData);
+            }
+            let key = (buf[offset] as u16) << 8 | (buf[offset + 1] as u16);
+            // keys must be strictly increasing (RFC 9460 §2.2)
+            if params.last().is_some_and(|last| last.key() >= key) {
+                return Err(&ParseError::InvalidRData);
+            }
+            let length = ((buf[offset + 2] as usize) << 8) | buf[offset + 3] as usize;
+            offset += 4;
+            if offset + length > end {
+                return Err(&ParseError::InvalidRData);
+            }
+            match SvcParam::from_wire(key, &buf[offset..offset + length]) {
+                Ok(param) => params.push(param),
+                Err(_) => return Err(&ParseError::InvalidRData),
+            }
+            offset += length;
+        }
+        Ok(SVCBData {
+            priority,
+            target,
+            params,
+        })
+    }
+
+    pub fn from_presentation(tokens: &[String], origin: &[String]) -> Result<SVCBData, ParseError> {
+        if tokens.len() < 2 {
+            return Err(ParseError::InvalidRData);
+        }
+        let priority = parse_number(&tokens[0])?;
+        let target = parse_presentation_name(&tokens[1], origin)?;
+
+        let mut params: Vec<SvcParam> = Vec::new();
+        for token in tokens[2..].iter() {
+            // the zone reader joins a quoted value to its key, so key= on
+            // its own has an empty value
+            let param = match token.find('=') {
+                Some(idx) => SvcParam::from_presentation(&token[..idx], Some(&token[idx + 1..]))?,
+                None => SvcParam::from_presentation(token, None)?,
+            };
+            if params.iter().any(|p| p.key() == param.key()) {
+                return Err(ParseError::InvalidRData);
+            }
+            params.push(param);
+        }
+        // keys must appear in increasing order on the wire
+        params.sort_by_key(|p| p.key());
+        // and every mandatory key must be present
+        for param in params.iter() {
+            if let SvcParam::Mandatory(keys) = param {
+                if keys.iter().any(|k| params.iter().all(|p| p.key() != *k)) {
+                    return Err(ParseError::InvalidRData);
+                }
+            }
+        }
+
+        Ok(SVCBData {
+            priority,
+            target,
+            params,
+        })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = self.priority.to_be_bytes().to_vec();
+        wire.extend(name_to_wire(&self.target));
+        for param in self.params.iter() {
+            let value = param.value_to_wire();
+            wire.extend_from_slice(&param.key().to_be_bytes());
+            wire.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            wire.extend(value);
+        }
+        wire
+    }
+
+    pub fn priority(&self) -> u16 {
+        self.priority
+    }
+
+    pub fn target(&self) -> &[String] {
+        &self.target
+    }
+
+    pub fn params(&self) -> &[SvcParam] {
+        &self.params
+    }
+}
+
+impl fmt::Display for SVCBData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut fmt_str = format!("{} {}", self.priority, fqdn(&self.target));
+        for param in self.params.iter() {
+            fmt_str.push_str(&format!(" {}", param));
+        }
+        write!(f, "{}", fmt_str)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SSHFPData {
+    algorithm: u8,
+    fp_type: u8,
+    fingerprint: Vec<u8>,
+}
+
+impl SSHFPData {
+    pub fn from_wire(buf: &[u8], offset: usize, rdlength: usize) -> SSHFPData {
+        SSHFPData {
+            algorithm: buf[offset],
+            fp_type: buf[offset + 1],
+            fingerprint: buf[offset + 2..offset + rdlength].to_owned(),
+        }
+    }
+
+    pub fn from_presentation(tokens: &[String]) -> Result<SSHFPData, ParseError> {
+        if tokens.len() < 3 {
+            return Err(ParseError::InvalidRData);
+        }
+        Ok(SSHFPData {
+            algorithm: parse_number(&tokens[0])?,
+            fp_type: parse_number(&tokens[1])?,
+            fingerprint: hex_decode(&tokens[2..].concat())?,
+        })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = vec![self.algorithm, self.fp_type];
+        wire.extend(self.fingerprint.iter());
+        wire
+    }
+}
+
+impl fmt::Display for SSHFPData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}",
+            self.algorithm,
+            self.fp_type,
+            hex_encode(&self.fingerprint)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TLSAData {
+    usage: u8,
+    selector: u8,
+    matching_type: u8,
+    data: Vec<u8>,
+}
+
+impl TLSAData {
+    pub fn from_wire(buf: &[u8], offset: usize, rdlength: usize) -> TLSAData {
+        TLSAData {
+            usage: buf[offset],
+            selector: buf[offset + 1],
+            matching_type: buf[offset + 2],
+            data: buf[offset + 3..offset + rdlength].to_owned(),
+        }
+    }
+
+    pub fn from_presentation(tokens: &[String]) -> Result<TLSAData, ParseError> {
+        if tokens.len() < 4 {
+            return Err(ParseError::InvalidRData);
+        }
+        Ok(TLSAData {
+            usage: parse_number(&tokens[0])?,
+            selector: parse_number(&tokens[1])?,
+            matching_type: parse_number(&tokens[2])?,
+            data: hex_decode(&tokens[3..].concat())?,
+        })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = vec![self.usage, self.selector, self.matching_type];
+        wire.extend(self.data.iter());
+        wire
+    }
+}
+
+impl fmt::Display for TLSAData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}",
+            self.usage,
+            self.selector,
+            self.matching_type,
+            hex_encode(&self.data)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OPENPGPKEYData {
+    key: Vec<u8>,
+}
+
+impl OPENPGPKEYData {
+    pub fn from_wire(buf: &[u8], offset: usize, rdlength: usize) -> OPENPGPKEYData {
+        OPENPGPKEYData {
+            key: buf[offset..offset + rdlength].to_owned(),
+        }
+    }
+
+    pub fn from_presentation(tokens: &[String]) -> Result<OPENPGPKEYData, ParseError> {
+        Ok(OPENPGPKEYData {
+            key: base64_decode(&tokens.concat())?,
+        })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        self.key.clone()
+    }
+}
+
+impl fmt::Display for OPENPGPKEYData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", base64_encode(&self.key))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CAAData {
+    flags: u8,
+    tag: Vec<u8>,
+    value: Vec<u8>,
+}
+
+impl CAAData {
+    pub fn from_wire(
+        buf: &[u8],
+        offset: usize,
+        rdlength: usize,
+    ) -> Result<CAAData, &dyn error::Error> {
+        let flags = buf[offset];
+        let (tag, value_offset) = extract_character_string(buf, offset + 1)?;
+        Ok(CAAData {
+            flags,
+            tag,
+            // like URI, the value is not length prefixed
+            value: buf[value_offset..offset + rdlength].to_owned(),
+        })
+    }
+
+    pub fn from_presentation(tokens: &[String]) -> Result<CAAData, ParseError> {
+        if tokens.len() != 3 {
+            return Err(ParseError::InvalidRData);
+        }
+        let tag = unescape(&tokens[1])?;
+        if tag.is_empty() || tag.len() > 15 || !tag.iter().all(u8::is_ascii_alphanumeric) {
+            return Err(ParseError::InvalidRData);
+        }
+        Ok(CAAData {
+            flags: parse_number(&tokens[0])?,
+            tag,
+            value: unescape(&tokens[2])?,
+        })
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = vec![self.flags, self.tag.len() as u8];
+        wire.extend(self.tag.iter());
+        wire.extend(self.value.iter());
+        wire
+    }
+
+    /// The issuer critical flag: CAs must refuse to issue if they do not
+    /// understand the tag.
+    pub fn critical(&self) -> bool {
+        self.flags & 0x80 != 0
+    }
+}
+
+impl fmt::Display for CAAData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}",
+            self.flags,
+            String::from_utf8_lossy(&self.tag),
+            fmt_character_string(&self.value)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RData {
+    A(AData),
+    NS(NSData),
+    CNAME(CNAMEData),
+    SOA(SOAData),
+    PTR(PTRData),
+    MX(MXData),
+    TXT(TXTData),
+    AAAA(AAAAData),
+    MD(MDData),
+    MF(MFData),
+    MB(MBData),
+    MG(MGData),
+    MR(MRData),
+    NULL(NULLData),
+    WKS(WKSData),
+    HINFO(HINFOData),
+    MINFO(MINFOData),
+    SRV(SRVData),
+    NAPTR(NAPTRData),
+    URI(URIData),
+    SVCB(SVCBData),
+    HTTPS(SVCBData),
+    SSHFP(SSHFPData),
+    TLSA(TLSAData),
+    OPENPGPKEY(OPENPGPKEYData),
+    CAA(CAAData),
+    /// A type that is not decoded, with its RDATA as received.
+    UNKNOWN(u16, Vec<u8>),
+}
+
+impl fmt::Display for RData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RData::A(a_data) => a_data.fmt(f),
+            RData::NS(ns_data) => ns_data.fmt(f),
+            RData::CNAME(cname_data) => cname_data.fmt(f),
+            RData::SOA(soa_data) => soa_data.fmt(f),
+            RData::PTR(ptr_data) => ptr_data.fmt(f),
+            RData::MX(mx_data) => mx_data.fmt(f),
+            RData::TXT(txt_data) => txt_data.fmt(f),
+            RData::AAAA(aaaa_data) => aaaa_data.fmt(f),
+            RData::MD(md_data) => md_data.fmt(f),
+            RData::MF(mf_data) => mf_data.fmt(f),
+            RData::MB(mb_data) => mb_data.fmt(f),
+            RData::MG(mg_data) => mg_data.fmt(f),
+            RData::MR(mr_data) => mr_data.fmt(f),
+            RData::NULL(null_data) => null_data.fmt(f),
+            RData::WKS(wks_data) => wks_data.fmt(f),
+            RData::HINFO(hinfo_data) => hinfo_data.fmt(f),
+            RData::MINFO(minfo_data) => minfo_data.fmt(f),
+            RData::SRV(srv_data) => srv_data.fmt(f),
+            RData::NAPTR(naptr_data) => naptr_data.fmt(f),
+            RData::URI(uri_data) => uri_data.fmt(f),
+            RData::SVCB(svcb_data) => svcb_data.fmt(f),
+            RData::HTTPS(https_data) => https_data.fmt(f),
+            RData::SSHFP(sshfp_data) => sshfp_data.fmt(f),
+            RData::TLSA(tlsa_data) => tlsa_data.fmt(f),
+            RData::OPENPGPKEY(openpgpkey_data) => openpgpkey_data.fmt(f),
+            RData::CAA(caa_data) => caa_data.fmt(f),
+            RData::UNKNOWN(_, data) => write!(f, "{}", fmt_generic(data)),
+        }
+    }
+}
+
+impl RData {
+    /// Render the RDATA in master file presentation format, with fully
+    /// qualified names and fields separated by single spaces.
+    pub fn to_presentation(&self) -> String {
+        match self {
+            RData::NS(ns_data) => fqdn(&ns_data.nsdname),
+            RData::CNAME(cname_data) => fqdn(&cname_data.cname),
+            RData::SOA(soa_data) => format!(
+                "{} {} {} {} {} {} {}",
+                fqdn(&soa_data.mname),
+                fqdn(&soa_data.rname),
+                soa_data.serial,
+                soa_data.refresh,
+                soa_data.retry,
+                soa_data.expire,
+                soa_data.minimum
+            ),
+            RData::PTR(ptr_data) => fqdn(&ptr_data.ptrdname),
+            RData::MX(mx_data) => format!("{} {}", mx_data.preference, fqdn(&mx_data.exchange)),
+            RData::MD(md_data) => fqdn(&md_data.madname),
+            RData::MF(mf_data) => fqdn(&mf_data.madname),
+            RData::MB(mb_data) => fqdn(&mb_data.madname),
+            RData::MG(mg_data) => fqdn(&mg_data.mgmname),
+            RData::MR(mr_data) => fqdn(&mr_data.newname),
+            RData::MINFO(minfo_data) => format!(
+                "{} {}",
+                fqdn(&minfo_data.rmailbx),
+                fqdn(&minfo_data.emailbx)
+            ),
+            RData::SRV(srv_data) => format!(
+                "{} {} {} {}",
+                srv_data.priority,
+                srv_data.weight,
+                srv_data.port,
+                fqdn(&srv_data.target)
+            ),
+            RData::NAPTR(naptr_data) => format!(
+                "{} {} {} {} {} {}",
+                naptr_data.order,
+                naptr_data.preference,
+                fmt_character_string(&naptr_data.flags),
+                fmt_character_string(&naptr_data.services),
+                fmt_character_string(&naptr_data.regexp),
+                fqdn(&naptr_data.replacement)
+            ),
+            _ => self.to_string().trim_end().replace('\t', " "),
+        }
+    }
+
+    /// Parse RDATA from the whitespace-separated fields of a master file
+    /// entry; relative names are completed with `origin`.
+    pub fn from_presentation(
+        rrtype: RRType,
+        tokens: &[String],
+        origin: &[String],
+    ) -> Result<RData, ParseError> {
+        if tokens.len() >= 2 && tokens[0] == "\\#" {
+            let wire = parse_generic(tokens)?;
+            return RData::from_wire(rrtype, &wire, 0, wire.len())
+                .map_err(|_| ParseError::InvalidRData);
+        }
+        match rrtype {
+            RRType::A => Ok(RData::A(AData::from_presentation(tokens)?)),
+            RRType::NS => Ok(RData::NS(NSData::from_presentation(tokens, origin)?)),
+            RRType::CNAME => Ok(RData::CNAME(CNAMEData::from_presentation(tokens, origin)?)),
+            RRType::SOA => Ok(RData::SOA(SOAData::from_presentation(tokens, origin)?)),
+            RRType::PTR => Ok(RData::PTR(PTRData::from_presentation(tokens, origin)?)),
+            RRType::MX => Ok(RData::MX(MXData::from_presentation(tokens, origin)?)),
+            RRType::TXT => Ok(RData::TXT(TXTData::from_presentation(tokens)?)),
+            RRType::AAAA => Ok(RData::AAAA(AAAAData::from_presentation(tokens)?)),
+            RRType::MD => Ok(RData::MD(MDData::from_presentation(tokens, origin)?)),
+            RRType::MF => Ok(RData::MF(MFData::from_presentation(tokens, origin)?)),
+            RRType::MB => Ok(RData::MB(MBData::from_presentation(tokens, origin)?)),
+            RRType::MG => Ok(RData::MG(MGData::from_presentation(tokens, origin)?)),
+            RRType::MR => Ok(RData::MR(MRData::from_presentation(tokens, origin)?)),
+            RRType::WKS => Ok(RData::WKS(WKSData::from_presentation(tokens)?)),
+            RRType::HINFO => Ok(RData::HINFO(HINFOData::from_presentation(tokens)?)),
+            RRType::MINFO => Ok(RData::MINFO(MINFOData::from_presentation(tokens, origin)?)),
+            RRType::SRV => Ok(RData::SRV(SRVData::from_presentation(tokens, origin)?)),
+            RRType::NAPTR => Ok(RData::NAPTR(NAPTRData::from_presentation(tokens, origin)?)),
+            RRType::URI => Ok(RData::URI(URIData::from_presentation(tokens)?)),
+            RRType::SVCB => Ok(RData::SVCB(SVCBData::from_presentation(tokens, origin)?)),
+            RRType::HTTPS => Ok(RData::HTTPS(SVCBData::from_presentation(tokens, origin)?)),
+            RRType::SSHFP => Ok(RData::SSHFP(SSHFPData::from_presentation(tokens)?)),
+            RRType::TLSA => Ok(RData::TLSA(TLSAData::from_presentation(tokens)?)),
+            RRType::OPENPGPKEY => Ok(RData::OPENPGPKEY(OPENPGPKEYData::from_presentation(
+                tokens,
+            )?)),
+            RRType::CAA => Ok(RData::CAA(CAAData::from_presentation(tokens)?)),
+            // NULL is only representable in the generic notation
+            _ => Err(ParseError::InvalidRData),
+        }
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        match self {
+            RData::A(a_data) => a_data.to_wire(),
+            RData::NS(ns_data) => ns_data.to_wire(),
+            RData::CNAME(cname_data) => cname_data.to_wire(),
+            RData::SOA(soa_data) => soa_data.to_wire(),
+            RData::PTR(ptr_data) => ptr_data.to_wire(),
+            RData::MX(mx_data) => mx_data.to_wire(),
+            RData::TXT(txt_data) => txt_data.to_wire(),
+            RData::AAAA(aaaa_data) => aaaa_data.to_wire(),
+            RData::MD(md_data) => md_data.to_wire(),
+            RData::MF(mf_data) => mf_data.to_wire(),
+            RData::MB(mb_data) => mb_data.to_wire(),
+            RData::MG(mg_data) => mg_data.to_wire(),
+            RData::MR(mr_data) => mr_data.to_wire(),
+            RData::NULL(null_data) => null_data.to_wire(),
+            RData::WKS(wks_data) => wks_data.to_wire(),
+            RData::HINFO(hinfo_data) => hinfo_data.to_wire(),
+            RData::MINFO(minfo_data) => minfo_data.to_wire(),
+            RData::SRV(srv_data) => srv_data.to_wire(),
+            RData::NAPTR(naptr_data) => naptr_data.to_wire(),
+            RData::URI(uri_data) => uri_data.to_wire(),
+            RData::SVCB(svcb_data) => svcb_data.to_wire(),
+            RData::HTTPS(https_data) => https_data.to_wire(),
+            RData::SSHFP(sshfp_data) => sshfp_data.to_wire(),
+            RData::TLSA(tlsa_data) => tlsa_data.to_wire(),
+            RData::OPENPGPKEY(openpgpkey_data) => openpgpkey_data.to_wire(),
+            RData::CAA(caa_data) => caa_data.to_wire(),
+            RData::UNKNOWN(_, data) => data.clone(),
+        }
+    }
+
+    /// The RDATA as signed by DNSSEC: no compression, names lowercased.
+    pub fn to_canonical_wire(&self) -> Vec<u8> {
+        let mut wire = Vec::new();
+        self.write_wire(&mut wire, &mut NameCompressor::new(Compression::Canonical));
+        wire
+    }
+
+    /// Append the RDATA to `wire`, which holds the message being written.
+    /// Names in the RFC 1035 types may be compressed (RFC 3597 §4); those of
+    /// SRV and NAPTR never are, but are lowercased in canonical form.
+    pub fn write_wire(&self, wire: &mut Vec<u8>, names: &mut NameCompressor) {
+        match self {
+            RData::NS(ns_data) => names.write_name(wire, &ns_data.nsdname),
+            RData::CNAME(cname_data) => names.write_name(wire, &cname_data.cname),
+            RData::SOA(soa_data) => {
+                names.write_name(wire, &soa_data.mname);
+                names.write_name(wire, &soa_data.rname);
+                for field in [
+                    soa_data.serial,
+                    soa_data.refresh,
+                    soa_data.retry,
+                    soa_data.expire,
+                    soa_data.minimum,
+                ]
+                .iter()
+                {
+                    wire.extend_from_slice(&field.to_be_bytes());
+                }
+            }
+            RData::PTR(ptr_data) => names.write_name(wire, &ptr_data.ptrdname),
+            RData::MX(mx_data) => {
+                wire.extend_from_slice(&mx_data.preference.to_be_bytes());
+                names.write_name(wire, &mx_data.exchange);
+            }
+            RData::MD(md_data) => names.write_name(wire, &md_data.madname),
+            RData::MF(mf_data) => names.write_name(wire, &mf_data.madname),
+            RData::MB(mb_data) => names.write_name(wire, &mb_data.madname),
+            RData::MG(mg_data) => names.write_name(wire, &mg_data.mgmname),
+            RData::MR(mr_data) => names.write_name(wire, &mr_data.newname),
+            RData::MINFO(minfo_data) => {
+                names.write_name(wire, &minfo_data.rmailbx);
+                names.write_name(wire, &minfo_data.emailbx);
+            }
+            RData::SRV(srv_data) => {
+                for field in [srv_data.priority, srv_data.weight, srv_data.port].iter() {
+                    wire.extend_from_slice(&field.to_be_bytes());
+                }
+                names.write_uncompressed(wire, &srv_data.target);
+            }
+            RData::NAPTR(naptr_data) => {
+                wire.extend_from_slice(&naptr_data.order.to_be_bytes());
+                wire.extend_from_slice(&naptr_data.preference.to_be_bytes());
+                for string in [&naptr_data.flags, &naptr_data.services, &naptr_data.regexp].iter() {
+                    wire.push(string.len() as u8);
+                    wire.extend(string.iter());
+                }
+                names.write_uncompressed(wire, &naptr_data.replacement);
+            }
+            _ => wire.extend(self.to_wire()),
+        }
+    }
+
+    pub fn from_wire(
+        rrtype: RRType,
+        buf: &[u8],
+        offset: usize,
+        rdlength: usize,
+    ) -> Result<RData, &dyn error::Error> {
+        // nothing may be read from beyond the RDATA; names in it can still
+        // point back into the rest of the message
+        let end = offset + rdlength;
+        let buf = buf.get(..end).ok_or(TRUNCATED)?;
+        // the fixed length fields every RDATA of the type starts with
+        let fixed = match rrtype {
+            RRType::A => 4,
+            RRType::AAAA => 16,
+            RRType::WKS => 5,
+            RRType::SRV => 6,
+            RRType::URI | RRType::NAPTR => 4,
+            RRType::TLSA => 3,
+            RRType::MX | RRType::SVCB | RRType::HTTPS | RRType::SSHFP => 2,
+            RRType::CAA => 1,
+            _ => 0,
+        };
+        // an address is all there is to A and AAAA
+        let exact = matches!(rrtype, RRType::A | RRType::AAAA);
+        if rdlength < fixed || (exact && rdlength != fixed) {
+            return Err(&ParseError::InvalidRData);
+        }
+
+        match rrtype {
+            RRType::A => Ok(RData::A(AData::from_wire(buf, offset))),
+            RRType::NS => {
+                let ns = NSData::from_wire(buf, offset)?;
+                Ok(RData::NS(ns))
+            }
+            RRType::CNAME => {
+                let cname = CNAMEData::from_wire(buf, offset)?;
+                Ok(RData::CNAME(cname))
+            }
+            RRType::SOA => {
+                let soa = SOAData::from_wire(buf, offset)?;
+                Ok(RData::SOA(soa))
+            }
+            RRType::PTR => {
+                let ptr = PTRData::from_wire(buf, offset)?;
+                Ok(RData::PTR(ptr))
+            }
+            RRType::MX => {
+                let mx = MXData::from_wire(buf, offset)?;
+                Ok(RData::MX(mx))
+            }
+            RRType::TXT => Ok(RData::TXT(TXTData::from_wire(buf, offset, rdlength)?)),
+            RRType::AAAA => Ok(RData::AAAA(AAAAData::from_wire(buf, offset))),
+            RRType::MD => Ok(RData::MD(MDData::from_wire(buf, offset)?)),
+            RRType::MF => Ok(RData::MF(MFData::from_wire(buf, offset)?)),
+            RRType::MB => Ok(RData::MB(MBData::from_wire(buf, offset)?)),
+            RRType::MG => Ok(RData::MG(MGData::from_wire(buf, offset)?)),
+            RRType::MR => Ok(RData::MR(MRData::from_wire(buf, offset)?)),
+            RRType::NULL => Ok(RData::NULL(NULLData::from_wire(buf, offset, rdlength))),
+            RRType::WKS => Ok(RData::WKS(WKSData::from_wire(buf, offset, rdlength))),
+            RRType::HINFO => Ok(RData::HINFO(HINFOData::from_wire(buf, offset)?)),
+            RRType::MINFO => Ok(RData::MINFO(MINFOData::from_wire(buf, offset)?)),
+            RRType::SRV => Ok(RData::SRV(SRVData::from_wire(buf, offset)?)),
+            RRType::NAPTR => Ok(RData::NAPTR(NAPTRData::from_wire(buf, offset)?)),
+            RRType::URI => Ok(RData::URI(URIData::from_wire(buf, offset, rdlength))),
+            RRType::SVCB => Ok(RData::SVCB(SVCBData::from_wire(buf, offset, rdlength)?)),
+            RRType::HTTPS => Ok(RData::HTTPS(SVCBData::from_wire(buf, offset, rdlength)?)),
+            RRType::SSHFP => Ok(RData::SSHFP(SSHFPData::from_wire(buf, offset, rdlength))),
+            RRType::TLSA => Ok(RData::TLSA(TLSAData::from_wire(buf, offset, rdlength))),
+            RRType::OPENPGPKEY => Ok(RData::OPENPGPKEY(OPENPGPKEYData::from_wire(
+                buf, offset, rdlength,
+            ))),
+            RRType::CAA => Ok(RData::CAA(CAAData::from_wire(buf, offset, rdlength)?)),
+            _ => Ok(RData::UNKNOWN(u16::from(rrtype), buf[offset..end].to_vec())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn tokens(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn wks_ports() {
+        let wks = WKSData::from_presentation(&tokens("192.0.2.1 tcp 21 25 80")).unwrap();
+        let wire = wks.to_wire();
+        assert_eq!(wire[..5], [192, 0, 2, 1, 6]);
+        assert_eq!(wire.len(), 5 + 11);
+
+        let parsed = WKSData::from_wire(&wire, 0, wire.len());
+        assert_eq!(parsed.ports(), vec![21, 25, 80]);
+        assert_eq!(parsed.to_string(), "192.0.2.1\ttcp 21 25 80");
+    }
+
+    #[test]
+    fn hinfo_round_trip() {
+        let wire = b"\x06PDP-11\x05UNIX\x00";
+        let hinfo = HINFOData::from_wire(&wire[..], 0).unwrap();
+        assert_eq!(hinfo.to_wire(), wire.to_vec());
+        assert_eq!(hinfo.to_string(), "\"PDP-11\"\t\"UNIX\\000\"");
+        let long = vec!["x".repeat(256), "UNIX".to_string()];
+        assert!(HINFOData::from_presentation(&long).is_err());
+    }
+
+    #[test]
+    fn minfo_presentation() {
+        let origin = vec!["example".to_string()];
+        let rdata =
+            RData::from_presentation(RRType::MINFO, &tokens("admin errors.example."), &origin)
+                .unwrap();
+        assert_eq!(rdata.to_presentation(), "admin.example. errors.example.");
+        let wire = rdata.to_wire();
+        let parsed = RData::from_wire(RRType::MINFO, &wire, 0, wire.len()).unwrap();
+        assert_eq!(parsed.to_presentation(), "admin.example. errors.example.");
+    }
+
+    #[test]
+    fn naptr_round_trip() {
+        let origin = vec!["example".to_string()];
+        let fields = vec![
+            "100".to_string(),
+            "10".to_string(),
+            "S".to_string(),
+            "SIP+D2U".to_string(),
+            "".to_string(),
+            "_sip._udp".to_string(),
+        ];
+        let rdata = RData::from_presentation(RRType::NAPTR, &fields, &origin).unwrap();
+        let wire = rdata.to_wire();
+        let parsed = RData::from_wire(RRType::NAPTR, &wire, 0, wire.len()).unwrap();
+        assert_eq!(
+            parsed.to_presentation(),
+            "100 10 \"S\" \"SIP+D2U\" \"\" _sip._udp.example."
+        );
+    }
+
+    #[test]
+    fn uri_target_is_not_a_character_string() {
+        let wire = b"\x00\x0a\x00\x01ftp://ftp1.example.com/public";
+        let uri = URIData::from_wire(&wire[..], 0, wire.len());
+        assert_eq!(uri.to_string(), "10\t1\t\"ftp://ftp1.example.com/public\"");
+        assert_eq!(uri.to_wire(), wire.to_vec());
+    }
+
+    #[test]
+    fn srv_weighted_order() {
+        let srv = |text: &str| SRVData::from_presentation(&tokens(text), &[]).unwrap();
+        let records = [
+            srv("20 0 5060 backup.example."),
+            srv("10 60 5060 big.example."),
+            srv("10 0 5060 never.example."),
+            srv("10 40 5060 small.example."),
+        ];
+        let refs: Vec<&SRVData> = records.iter().collect();
+
+        let mut rng = StdRng::seed_from_u64(2782);
+        let mut big_first = 0;
+        let mut never_first = 0;
+        for _ in 0..1000 {
+            let ordered = order_srv_targets(&refs, &mut rng);
+            assert_eq!(ordered.len(), 4);
+            assert!(ordered[..3].iter().all(|srv| srv.priority() == 10));
+            assert_eq!(ordered[3].target()[0], "backup");
+            match ordered[0].target()[0].as_str() {
+                "big" => big_first += 1,
+                "never" => never_first += 1,
+                _ => {}
+            }
+        }
+        // 600 and about 10 of 1000 expected; the bounds are several standard
+        // deviations wide so that they hold whatever the generator
+        assert!((500..700).contains(&big_first), "big first {} times", big_first);
+        assert!(never_first < 50, "never first {} times", never_first);
+    }
+
+    #[test]
+    fn svcb_presentation_round_trip() {
+        let fields = tokens(
+            "1 . ipv6hint=2001:db8::1,2001:db8::53:1 alpn=h2,h3 mandatory=alpn,ipv4hint \
+             no-default-alpn port=8443 ipv4hint=192.0.2.1 ech=AEn+DQBFKwAgACABWIHUGj4u \
+             key667=hello\\210",
+        );
+        let rdata = RData::from_presentation(RRType::HTTPS, &fields, &[]).unwrap();
+        let expected = "1 . mandatory=alpn,ipv4hint alpn=h2,h3 no-default-alpn port=8443 \
+                        ipv4hint=192.0.2.1 ech=AEn+DQBFKwAgACABWIHUGj4u \
+                        ipv6hint=2001:db8::1,2001:db8::53:1 key667=\"hello\\210\"";
+        assert_eq!(rdata.to_presentation(), expected);
+
+        let wire = rdata.to_wire();
+        let parsed = RData::from_wire(RRType::HTTPS, &wire, 0, wire.len()).unwrap();
+        assert_eq!(parsed.to_presentation(), expected);
+        assert_eq!(parsed.to_wire(), wire);
+    }
+
+    #[test]
+    fn svcb_wire() {
+        // RFC 9460 appendix D.2, figure 4: "1 foo.example.com. port=53"
+        let wire = b"\x00\x01\x03foo\x07example\x03com\x00\x00\x03\x00\x02\x00\x35";
+        let svcb = SVCBData::from_wire(&wire[..], 0, wire.len()).unwrap();
+        assert_eq!(svcb.to_string(), "1 foo.example.com. port=53");
+
+        // alias mode, with a port that has the wrong length
+        let bad = b"\x00\x00\x00\x00\x03\x00\x01\x35";
+        assert!(SVCBData::from_wire(&bad[..], 0, bad.len()).is_err());
+
+        // port before alpn, then port twice
+        let unordered = b"\x00\x01\x00\x00\x03\x00\x02\x00\x35\x00\x01\x00\x03\x02h2";
+        assert!(SVCBData::from_wire(&unordered[..], 0, unordered.len()).is_err());
+        let repeated = b"\x00\x01\x00\x00\x03\x00\x02\x00\x35\x00\x03\x00\x02\x00\x35";
+        assert!(SVCBData::from_wire(&repeated[..], 0, repeated.len()).is_err());
+    }
+
+    #[test]
+    fn svcb_escaped_alpn() {
+        let fields = tokens("16 foo.example.org. alpn=f\\\\oo\\,bar,h2");
+        let svcb = SVCBData::from_presentation(&fields, &[]).unwrap();
+        match &svcb.params()[0] {
+            SvcParam::Alpn(ids) => {
+                assert_eq!(ids, &vec![b"f\\oo,bar".to_vec(), b"h2".to_vec()]);
+            }
+            _ => panic!("expected alpn"),
+        }
+        assert_eq!(svcb.to_string(), "16 foo.example.org. alpn=f\\\\oo\\,bar,h2");
+        assert!(SVCBData::from_presentation(&tokens("1 . port=1 port=2"), &[]).is_err());
+        assert!(SVCBData::from_presentation(&tokens("1 . alpn=h2,,h3"), &[]).is_err());
+        assert!(SVCBData::from_presentation(&tokens("1 . alpn="), &[]).is_err());
+    }
+
+    #[test]
+    fn svcb_mandatory_keys() {
+        let svcb = |text: &str| SVCBData::from_presentation(&tokens(text), &[]);
+        let sorted = svcb("1 . mandatory=port,alpn alpn=h2 port=443").unwrap();
+        assert_eq!(sorted.to_string(), "1 . mandatory=alpn,port alpn=h2 port=443");
+        // missing, repeated and self-referencing keys
+        assert!(svcb("1 . mandatory=port alpn=h2").is_err());
+        assert!(svcb("1 . mandatory=alpn,alpn alpn=h2").is_err());
+        assert!(svcb("1 . mandatory=mandatory,alpn alpn=h2").is_err());
+    }
+
+    #[test]
+    fn svcb_empty_value() {
+        for text in ["1 . key65000= port=443", "1 . port=443 key65000="].iter() {
+            let svcb = SVCBData::from_presentation(&tokens(text), &[]).unwrap();
+            assert_eq!(svcb.params().len(), 2);
+            assert_eq!(svcb.to_string(), "1 . port=443 key65000=\"\"");
+        }
+    }
+
+    #[test]
+    fn security_types_round_trip() {
+        for (rrtype, text, presentation) in [
+            (
+                RRType::CAA,
+                "128 issue letsencrypt.org;validationmethods=dns-01",
+                "128 issue \"letsencrypt.org;validationmethods=dns-01\"",
+            ),
+            (
+                RRType::TLSA,
+                "3 1 1 0d6fce3368 6f9b5fb2",
+                "3 1 1 0D6FCE33686F9B5FB2",
+            ),
+            (
+                RRType::SSHFP,
+                "4 2 123456789abcdef6",
+                "4 2 123456789ABCDEF6",
+            ),
+            (RRType::OPENPGPKEY, "mQENBF Vn3xs=", "mQENBFVn3xs="),
+        ]
+        .iter()
+        {
+            let rdata = RData::from_presentation(*rrtype, &tokens(text), &[]).unwrap();
+            assert_eq!(rdata.to_presentation(), *presentation);
+            let wire = rdata.to_wire();
+            let parsed = RData::from_wire(*rrtype, &wire, 0, wire.len()).unwrap();
+            assert_eq!(parsed.to_presentation(), *presentation);
+        }
+    }
+
+    #[test]
+    fn caa_flags_and_tag() {
+        let caa = CAAData::from_presentation(&tokens("128 tbs Unknown")).unwrap();
+        assert!(caa.critical());
+        assert!(CAAData::from_presentation(&tokens("0 is-sue x")).is_err());
+    }
+
+    #[test]
+    fn txt_character_strings() {
+        let wire = b"\x0bv=DKIM1; k=\x04rsa;\x00\x03\x00\"\\";
+        let txt = TXTData::from_wire(&wire[..], 0, wire.len()).unwrap();
+        assert_eq!(txt.strings().len(), 4);
+        assert_eq!(
+            txt.to_string(),
+            "\"v=DKIM1; k=\" \"rsa;\" \"\" \"\\000\\\"\\\\\""
+        );
+        assert_eq!(txt.joined(), b"v=DKIM1; k=rsa;\x00\"\\".to_vec());
+        assert_eq!(txt.to_wire(), wire.to_vec());
+
+        // a length prefix running past the end of RDATA
+        let short = b"\x05abc";
+        assert!(TXTData::from_wire(&short[..], 0, short.len()).is_err());
+    }
+
+    #[test]
+    fn address_length_is_exact() {
+        let wire = [192, 0, 2, 1, 0];
+        assert!(RData::from_wire(RRType::A, &wire, 0, 4).is_ok());
+        assert!(RData::from_wire(RRType::A, &wire, 0, 5).is_err());
+        assert!(RData::from_wire(RRType::AAAA, &[0; 17], 0, 17).is_err());
+    }
+
+    #[test]
+    fn undecoded_rdata_is_kept() {
+        // an OPT record outside the additional section
+        let wire = [0, 8, 0, 4, 1, 24, 192, 0];
+        let rdata = RData::from_wire(RRType::OPT, &wire, 0, wire.len()).unwrap();
+        assert_eq!(rdata.to_wire(), wire.to_vec());
+        assert_eq!(rdata.to_string(), "\\# 8 000800040118c000");
+
+        // and printed so that it reads back the same
+        let fields = tokens("\\# 4 deadbeef");
+        let ds = RData::from_presentation(RRType::Unknown(43), &fields, &[]).unwrap();
+        assert_eq!(ds, RData::UNKNOWN(43, vec![0xde, 0xad, 0xbe, 0xef]));
+        assert_eq!(ds.to_presentation(), "\\# 4 deadbeef");
+    }
+
+    #[test]
+    fn null_generic() {
+        let rdata = RData::from_presentation(RRType::NULL, &tokens("\\# 2 beef"), &[]).unwrap();
+        assert_eq!(rdata.to_string(), "\\# 2 beef");
+        assert!(RData::from_presentation(RRType::NULL, &tokens("beef"), &[]).is_err());
     }
 }
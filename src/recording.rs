@@ -0,0 +1,340 @@
+/* Queries and their replies saved to a file, one exchange per line, so that
+ * a run can be replayed later without the network:
+ *
+ *   when <TAB> server <TAB> transport <TAB> elapsed <TAB> query <TAB> reply
+ *
+ * `when` is in seconds since the epoch and `elapsed` in microseconds; the
+ * query and reply are their wire form in hex.  Blank lines and lines
+ * starting with # are ignored. */
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::message::Message;
+use crate::resolver::Resolver;
+use crate::response::{Response, Transport};
+use crate::rr::RRType;
+use crate::utils::{hex_decode, hex_encode};
+
+/// The questions of a query, in a form that compares without regard to
+/// the case of names.
+type QuestionKey = Vec<(Vec<String>, u16, u16)>;
+
+fn question_key(query: &Message) -> QuestionKey {
+    query
+        .question
+        .iter()
+        .map(|q| {
+            let name = q
+                .qname
+                .iter()
+                .filter(|l| !l.is_empty())
+                .map(|l| l.to_lowercase())
+                .collect();
+            (name, u16::from(q.qtype), q.qclass)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exchange {
+    /// When the query was sent.
+    pub when: SystemTime,
+    /// The address the reply came from, with port.
+    pub server: String,
+    pub transport: Transport,
+    pub elapsed: Duration,
+    pub query: Vec<u8>,
+    pub reply: Vec<u8>,
+}
+
+impl Exchange {
+    /// One line of a recording, without the newline.
+    pub fn to_line(&self) -> String {
+        let when = self.when.duration_since(UNIX_EPOCH).unwrap_or_default();
+        format!(
+            "{}.{:06}\t{}\t{}\t{}\t{}\t{}",
+            when.as_secs(),
+            when.subsec_micros(),
+            self.server,
+            self.transport,
+            self.elapsed.as_micros(),
+            hex_encode(&self.query),
+            hex_encode(&self.reply)
+        )
+    }
+
+    pub fn from_line(line: &str) -> Result<Exchange, String> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 6 {
+            return Err(format!("expected 6 fields, found {}", fields.len()));
+        }
+        let when = parse_time(fields[0]).ok_or_else(|| format!("invalid time {}", fields[0]))?;
+        let transport = match fields[2] {
+            "UDP" => Transport::Udp,
+            "TCP" => Transport::Tcp,
+            other => return Err(format!("unknown transport {}", other)),
+        };
+        let elapsed = fields[3]
+            .parse::<u64>()
+            .map_err(|_| format!("invalid elapsed time {}", fields[3]))?;
+        let query = hex_decode(fields[4]).map_err(|_| "invalid query".to_string())?;
+        let reply = hex_decode(fields[5]).map_err(|_| "invalid reply".to_string())?;
+        Ok(Exchange {
+            when: UNIX_EPOCH + when,
+            server: fields[1].to_string(),
+            transport,
+            elapsed: Duration::from_micros(elapsed),
+            query,
+            reply,
+        })
+    }
+}
+
+/// Seconds with up to six decimals, e.g. `1582502400.25`.
+fn parse_time(text: &str) -> Option<Duration> {
+    let (secs, fraction) = match text.find('.') {
+        Some(dot) => (&text[..dot], &text[dot + 1..]),
+        None => (text, ""),
+    };
+    if fraction.len() > 6 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let micros = format!("{:0<6}", fraction).parse::<u32>().ok()?;
+    Some(Duration::new(secs.parse().ok()?, micros * 1000))
+}
+
+/// Read all the exchanges in a recording.
+pub fn read_file(path: &str) -> io::Result<Vec<Exchange>> {
+    let text = fs::read_to_string(path)?;
+    let mut exchanges = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let exchange = Exchange::from_line(line).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} line {}: {}", path, number + 1, e),
+            )
+        })?;
+        exchanges.push(exchange);
+    }
+    Ok(exchanges)
+}
+
+/// Somewhere to keep queries and the replies to them.
+pub trait Record: Send + Sync {
+    fn record(&self, query: &Message, response: &Response) -> io::Result<()>;
+}
+
+/// Give an exchange to each of `recorders`.  Failing to record is only
+/// warned about: the reply is still good, so the lookup goes on.
+pub fn record_all(recorders: &[Arc<dyn Record>], query: &Message, response: &Response) {
+    for recorder in recorders.iter() {
+        if let Err(e) = recorder.record(query, response) {
+            eprintln!("Failed to record the reply from {}: {}", response.server, e);
+        }
+    }
+}
+
+/// Appends each exchange to a recording as it happens.
+pub struct Recorder {
+    file: Mutex<File>,
+}
+
+impl Recorder {
+    /// Open `path` for appending, creating it if need be.
+    pub fn create(path: &str) -> io::Result<Recorder> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Recorder {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl Record for Recorder {
+    /// The query is written with the ID of the reply, in case it was
+    /// changed on the way out.
+    fn record(&self, query: &Message, response: &Response) -> io::Result<()> {
+        let mut query = query.clone();
+        query.id = response.message.id;
+        let exchange = Exchange {
+            when: response.when,
+            server: response.server.clone(),
+            transport: response.transport,
+            elapsed: response.elapsed,
+            query: query.to_wire(),
+            reply: response.wire.clone(),
+        };
+        let line = exchange.to_line() + "\n";
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())?;
+        file.flush()
+    }
+}
+
+/// Answers queries from a recording instead of the network.
+pub struct Replay {
+    exchanges: Vec<(QuestionKey, Exchange)>,
+    /// How many times each question has been asked, per server, so that
+    /// repeated queries get the recorded replies in turn.
+    asked: Mutex<HashMap<(QuestionKey, String), usize>>,
+}
+
+impl Replay {
+    pub fn new(exchanges: Vec<Exchange>) -> io::Result<Replay> {
+        let exchanges = exchanges
+            .into_iter()
+            .map(|exchange| {
+                let query = Message::from_wire(&exchange.query)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+                Ok((question_key(&query), exchange))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(Replay {
+            exchanges,
+            asked: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn open(path: &str) -> io::Result<Replay> {
+        Replay::new(read_file(path)?)
+    }
+
+    /// The recorded reply to a query with the same questions, preferring
+    /// ones from `server`.  When a question was recorded more than once the
+    /// replies are given in the order they were recorded, the last one
+    /// being repeated once they run out.  The reply carries the ID of
+    /// `query`.
+    pub fn lookup_message(&self, query: &Message, server: &str) -> io::Result<Response> {
+        let key = question_key(query);
+        let same_question: Vec<&Exchange> = self
+            .exchanges
+            .iter()
+            .filter(|(recorded, _)| *recorded == key)
+            .map(|(_, exchange)| exchange)
+            .collect();
+        let same_server: Vec<&Exchange> = same_question
+            .iter()
+            .filter(|exchange| exchange.server == server)
+            .cloned()
+            .collect();
+        let candidates = if same_server.is_empty() {
+            same_question
+        } else {
+            same_server
+        };
+        if candidates.is_empty() {
+            let names: Vec<String> = query.question.iter().map(|q| q.to_string()).collect();
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no recorded reply for {}", names.join(", ")),
+            ));
+        }
+
+        let exchange = {
+            let mut asked = self.asked.lock().unwrap();
+            let count = asked.entry((key, server.to_string())).or_insert(0);
+            *count += 1;
+            candidates[(*count - 1).min(candidates.len() - 1)]
+        };
+        let mut reply = exchange.reply.clone();
+        if reply.len() >= 2 {
+            reply[..2].copy_from_slice(&query.id.to_be_bytes());
+        }
+        Response::new(
+            &reply,
+            exchange.server.clone(),
+            exchange.transport,
+            exchange.when,
+            exchange.elapsed,
+        )
+    }
+}
+
+impl Resolver for Replay {
+    fn resolve(&self, name: &str, rrtype: RRType) -> io::Result<Message> {
+        let query = crate::new_query(name.to_string(), rrtype.to_string());
+        self.lookup_message(&query, "")
+            .map(|response| response.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange(server: &str, address: u8) -> Exchange {
+        let query = crate::new_query("example.com".to_string(), "A".to_string());
+        let mut reply = query.clone();
+        reply.meta.qr = crate::message::QR::R;
+        let tokens = vec![format!("192.0.2.{}", address)];
+        let rdata = crate::rdata::RData::from_presentation(RRType::A, &tokens, &[]).unwrap();
+        reply.answer.push(crate::rr::RR::new(
+            query.question[0].qname.clone(),
+            RRType::A,
+            crate::rr::Class::IN,
+            crate::ttl::Ttl::from_secs(300),
+            rdata,
+        ));
+        reply.ancount = 1;
+        Exchange {
+            when: UNIX_EPOCH + Duration::from_micros(1_582_502_400_250_000),
+            server: server.to_string(),
+            transport: Transport::Udp,
+            elapsed: Duration::from_micros(12_500),
+            query: query.to_wire(),
+            reply: reply.to_wire(),
+        }
+    }
+
+    fn address(response: &Response) -> String {
+        response.message.answer[0].rdata().to_presentation()
+    }
+
+    #[test]
+    fn recording_line() {
+        let recorded = exchange("192.0.2.53:53", 1);
+        let line = recorded.to_line();
+        assert!(line.starts_with("1582502400.250000\t192.0.2.53:53\tUDP\t12500\t"));
+        assert_eq!(Exchange::from_line(&line), Ok(recorded));
+        assert!(Exchange::from_line("1582502400\t192.0.2.53:53\tUDP\t12500\t00").is_err());
+        assert!(Exchange::from_line("now\t192.0.2.53:53\tUDP\t12500\t00\t00").is_err());
+    }
+
+    #[test]
+    fn recording_replay() {
+        let replay = Replay::new(vec![
+            exchange("192.0.2.53:53", 1),
+            exchange("192.0.2.53:53", 2),
+            exchange("198.51.100.53:53", 3),
+        ])
+        .unwrap();
+        let query = crate::new_query("EXAMPLE.com".to_string(), "A".to_string());
+
+        // the replies from a server come in turn, with the query's ID
+        let first = replay.lookup_message(&query, "192.0.2.53:53").unwrap();
+        assert_eq!(first.message.id, query.id);
+        assert_eq!(first.elapsed, Duration::from_micros(12_500));
+        assert_eq!(address(&first), "192.0.2.1");
+        for _ in 0..2 {
+            let next = replay.lookup_message(&query, "192.0.2.53:53").unwrap();
+            assert_eq!(address(&next), "192.0.2.2");
+        }
+        let other = replay.lookup_message(&query, "198.51.100.53:53").unwrap();
+        assert_eq!(address(&other), "192.0.2.3");
+        assert_eq!(other.server, "198.51.100.53:53");
+
+        // any server will do when the one asked was not recorded
+        let reply = replay.resolve("example.com", RRType::A).unwrap();
+        assert_eq!(reply.answer[0].rdata().to_presentation(), "192.0.2.1");
+
+        let missing = replay.resolve("example.com", RRType::MX).unwrap_err();
+        assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+    }
+}
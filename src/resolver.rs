@@ -0,0 +1,154 @@
+/* The lookup interface used by the commands that need more than one query
+ * to do their job, so that they can be pointed at the network or, in
+ * tests, at canned answers. */
+
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::cache::Cache;
+use crate::message::Message;
+use crate::recording::{record_all, Record};
+use crate::rr::{Class, RRType};
+
+pub trait Resolver {
+    /// Look up `name` (in presentation form, e.g. "example.com") for the
+    /// given type and return the reply.
+    fn resolve(&self, name: &str, rrtype: RRType) -> io::Result<Message>;
+}
+
+impl<R: Resolver + ?Sized> Resolver for Box<R> {
+    fn resolve(&self, name: &str, rrtype: RRType) -> io::Result<Message> {
+        (**self).resolve(name, rrtype)
+    }
+}
+
+/// Sends each query as a single UDP exchange with one server.
+pub struct UdpResolver {
+    server: String,
+    recorders: Vec<Arc<dyn Record>>,
+}
+
+impl UdpResolver {
+    /// `server` is an address with port, e.g. "192.0.2.53:53".
+    pub fn new(server: String) -> UdpResolver {
+        UdpResolver {
+            server,
+            recorders: Vec::new(),
+        }
+    }
+
+    /// Record every query and its reply; may be given more than once.
+    pub fn with_recorder(mut self, recorder: Arc<dyn Record>) -> UdpResolver {
+        self.recorders.push(recorder);
+        self
+    }
+}
+
+impl Resolver for UdpResolver {
+    fn resolve(&self, name: &str, rrtype: RRType) -> io::Result<Message> {
+        let query = crate::new_query(name.to_string(), rrtype.to_string());
+        let response = crate::query_message(&query, self.server.clone())?;
+        record_all(&self.recorders, &query, &response);
+        Ok(response.message)
+    }
+}
+
+/// Answers from a cache where it can, asking another resolver otherwise
+/// and keeping what it says.
+pub struct CachingResolver<R: Resolver> {
+    inner: R,
+    cache: Mutex<Cache>,
+}
+
+impl<R: Resolver> CachingResolver<R> {
+    /// See `Cache::new` for `max_entries`.
+    pub fn new(inner: R, max_entries: usize) -> CachingResolver<R> {
+        CachingResolver {
+            inner,
+            cache: Mutex::new(Cache::new(max_entries)),
+        }
+    }
+
+    pub fn cache(&self) -> MutexGuard<'_, Cache> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<R: Resolver> Resolver for CachingResolver<R> {
+    fn resolve(&self, name: &str, rrtype: RRType) -> io::Result<Message> {
+        let labels = crate::parse_name(name.to_string());
+        if let Some(reply) = self.cache().get(&labels, rrtype, Class::IN) {
+            return Ok(reply);
+        }
+        let reply = self.inner.resolve(name, rrtype)?;
+        self.cache().insert(&reply);
+        Ok(reply)
+    }
+}
+
+/// Answers from the records of a master file, for tests.
+#[cfg(test)]
+pub struct ZoneResolver {
+    pub zone: &'static str,
+}
+
+#[cfg(test)]
+impl Resolver for ZoneResolver {
+    fn resolve(&self, name: &str, rrtype: RRType) -> io::Result<Message> {
+        use crate::message::RCode;
+        use crate::rr::RR;
+
+        let name = name.trim_end_matches('.').to_lowercase();
+        let records = crate::zone::parse_str(self.zone, ".").unwrap();
+        let exists = records.iter().any(|rr| rr.name().join(".") == name);
+        let answer: Vec<RR> = records
+            .into_iter()
+            .filter(|rr| rr.name().join(".") == name && rr.rrtype() == rrtype)
+            .collect();
+
+        let labels = name.split('.').map(String::from).collect();
+        let mut reply = Message::new(labels, rrtype.to_string());
+        reply.ancount = answer.len() as u16;
+        reply.answer = answer;
+        if !exists {
+            reply.meta.rcode = RCode::NameError;
+        }
+        Ok(reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// Counts the lookups that get through to the zone.
+    struct CountingResolver {
+        zone: ZoneResolver,
+        lookups: Cell<usize>,
+    }
+
+    impl Resolver for CountingResolver {
+        fn resolve(&self, name: &str, rrtype: RRType) -> io::Result<Message> {
+            self.lookups.set(self.lookups.get() + 1);
+            self.zone.resolve(name, rrtype)
+        }
+    }
+
+    #[test]
+    fn caching_resolver() {
+        let counting = CountingResolver {
+            zone: ZoneResolver {
+                zone: "example.com. 300 IN A 192.0.2.1\n",
+            },
+            lookups: Cell::new(0),
+        };
+        let resolver = CachingResolver::new(counting, 16);
+        for _ in 0..3 {
+            let reply = resolver.resolve("Example.com", RRType::A).unwrap();
+            assert_eq!(reply.answer[0].rdata().to_presentation(), "192.0.2.1");
+        }
+        assert_eq!(resolver.inner.lookups.get(), 1);
+        assert_eq!(resolver.cache().len(), 1);
+    }
+}
@@ -0,0 +1,222 @@
+/* A reply together with how it was obtained: which server sent it, over
+ * what, when, how long it took and how big it was. */
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::message::{Message, QR};
+use crate::rr::{Class, RR};
+use crate::utils::fqdn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Udp,
+    Tcp,
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Transport::Udp => write!(f, "UDP"),
+            Transport::Tcp => write!(f, "TCP"),
+        }
+    }
+}
+
+pub struct Response {
+    pub message: Message,
+    /// The address the reply came from, with port.
+    pub server: String,
+    pub transport: Transport,
+    /// When the query was sent.
+    pub when: SystemTime,
+    /// From sending the query to receiving the reply.
+    pub elapsed: Duration,
+    /// The reply as received.
+    pub wire: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(
+        wire: &[u8],
+        server: String,
+        transport: Transport,
+        when: SystemTime,
+        elapsed: Duration,
+    ) -> io::Result<Response> {
+        let message = Message::from_wire(wire)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        Ok(Response {
+            message,
+            server,
+            transport,
+            when,
+            elapsed,
+            wire: wire.to_vec(),
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.wire.len()
+    }
+
+    /// The response as a single JSON object.
+    pub fn to_json(&self) -> String {
+        let message = &self.message;
+        let meta = &message.meta;
+        let questions: Vec<String> = message
+            .question
+            .iter()
+            .map(|q| {
+                // classes this crate does not know get the RFC 3597 name
+                let class = Class::try_from(q.qclass)
+                    .map(|class| format!("{:?}", class))
+                    .unwrap_or_else(|_| format!("CLASS{}", q.qclass));
+                format!(
+                    "{{\"name\":{},\"type\":{},\"class\":{}}}",
+                    json_string(&fqdn(&q.qname)),
+                    json_string(&q.qtype.to_string()),
+                    json_string(&class)
+                )
+            })
+            .collect();
+        let edns = match &message.edns {
+            Some(edns) => format!(
+                "{{\"udp_payload_size\":{},\"version\":{},\"dnssec_ok\":{},\"extended_rcode\":{},\"options\":[{}]}}",
+                edns.udp_payload_size,
+                edns.version,
+                edns.dnssec_ok,
+                edns.extended_rcode,
+                edns.options
+                    .iter()
+                    .map(|o| json_string(&o.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            None => "null".to_string(),
+        };
+
+        format!(
+            "{{\"server\":{},\"transport\":{},\"when\":{},\"elapsed_ms\":{:.3},\"size\":{},\
+             \"message\":{{\"id\":{},\"qr\":{},\"opcode\":{},\"aa\":{},\"tc\":{},\"rd\":{},\"ra\":{},\
+             \"z\":{},\"rcode\":{},\"question\":[{}],\"answer\":{},\"authority\":{},\"additional\":{},\
+             \"edns\":{}}}}}",
+            json_string(&self.server),
+            json_string(&self.transport.to_string()),
+            self.when
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            self.elapsed.as_secs_f64() * 1000.0,
+            self.size(),
+            message.id,
+            matches!(meta.qr, QR::R),
+            meta.opcode,
+            meta.aa,
+            meta.tc,
+            meta.rd,
+            meta.ra,
+            meta.z,
+            json_string(&format!("{:?}", meta.rcode)),
+            questions.join(","),
+            json_records(&message.answer),
+            json_records(&message.authority),
+            json_records(&message.additional),
+            edns
+        )
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\n\nResponse\nSERVER: {} ({}); TIME: {} ms; SIZE: {} bytes",
+            self.message,
+            self.server,
+            self.transport,
+            self.elapsed.as_millis(),
+            self.size()
+        )
+    }
+}
+
+/// Quote and escape text as a JSON string.
+pub fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn json_records(records: &[RR]) -> String {
+    let records: Vec<String> = records
+        .iter()
+        .map(|rr| {
+            format!(
+                "{{\"name\":{},\"ttl\":{},\"class\":{},\"type\":{},\"data\":{}}}",
+                json_string(&fqdn(rr.name())),
+                rr.ttl().as_secs(),
+                json_string(&format!("{:?}", rr.class())),
+                json_string(&rr.rrtype().to_string()),
+                json_string(&rr.rdata().to_presentation())
+            )
+        })
+        .collect();
+    format!("[{}]", records.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response() -> Response {
+        let msg: [u8; 45] = [
+            0x3e, 0x1d, 0x81, 0x80, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x07, 0x65,
+            0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x03, 0x63, 0x6f, 0x6d, 0x00, 0x00, 0x01, 0x00,
+            0x01, 0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x1c, 0x3e, 0x00, 0x04, 0x5d,
+            0xb8, 0xd8, 0x22,
+        ];
+        Response::new(
+            &msg,
+            "192.0.2.53:53".to_string(),
+            Transport::Udp,
+            UNIX_EPOCH + Duration::from_secs(1_582_502_400),
+            Duration::from_micros(12_500),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn response_display() {
+        assert!(response()
+            .to_string()
+            .ends_with("\n\nResponse\nSERVER: 192.0.2.53:53 (UDP); TIME: 12 ms; SIZE: 45 bytes"));
+    }
+
+    #[test]
+    fn response_json() {
+        assert_eq!(
+            response().to_json(),
+            "{\"server\":\"192.0.2.53:53\",\"transport\":\"UDP\",\"when\":1582502400,\
+             \"elapsed_ms\":12.500,\"size\":45,\"message\":{\"id\":15901,\"qr\":true,\
+             \"opcode\":0,\"aa\":false,\"tc\":false,\"rd\":true,\"ra\":true,\"z\":0,\
+             \"rcode\":\"NoError\",\"question\":[{\"name\":\"example.com.\",\"type\":\"A\",\
+             \"class\":\"IN\"}],\"answer\":[{\"name\":\"example.com.\",\"ttl\":7230,\"class\":\"IN\",\
+             \"type\":\"A\",\"data\":\"93.184.216.34\"}],\"authority\":[],\"additional\":[],\
+             \"edns\":null}}"
+        );
+        assert_eq!(json_string("a\"b\\c\u{1}"), "\"a\\\"b\\\\c\\u0001\"");
+    }
+}
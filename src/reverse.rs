@@ -0,0 +1,109 @@
+/* Names under in-addr.arpa (RFC 1035 §3.5) and ip6.arpa (RFC 3596 §2.5)
+ * for addresses and networks, so that PTR lookups can be made without
+ * spelling out the reversed name. */
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::utils::ipv6_nibbles;
+
+/// The name of an address's PTR records, e.g. 34.216.184.93.in-addr.arpa
+/// for 93.184.216.34.
+pub fn reverse_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => network_v4(ip, 32),
+        IpAddr::V6(ip) => network_v6(ip, 128).unwrap(),
+    }
+}
+
+/// Octet-aligned networks get the name of their zone.  Other prefixes get
+/// the RFC 2317 classless delegation name, in which the partial octet is
+/// written as "<first value>/<prefix length>", e.g. 64/26.2.0.192.in-addr.arpa.
+fn network_v4(ip: Ipv4Addr, prefix: u8) -> String {
+    let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+    let octets = Ipv4Addr::from(u32::from(ip) & mask).octets();
+
+    let whole = (prefix / 8) as usize;
+    let mut labels: Vec<String> = octets[..whole].iter().map(|o| o.to_string()).collect();
+    if !prefix.is_multiple_of(8) {
+        labels.push(format!("{}/{}", octets[whole], prefix));
+    }
+    labels.reverse();
+    labels.push("in-addr.arpa".to_string());
+    labels.join(".")
+}
+
+/// ip6.arpa names are nibble-aligned, there is no classless convention.
+fn network_v6(ip: Ipv6Addr, prefix: u8) -> Result<String, String> {
+    if !prefix.is_multiple_of(4) {
+        return Err(format!(
+            "IPv6 prefix length {} is not a multiple of 4",
+            prefix
+        ));
+    }
+    let mut labels = ipv6_nibbles(ip);
+    labels.truncate((prefix / 4) as usize);
+    labels.reverse();
+    labels.push("ip6.arpa".to_string());
+    Ok(labels.join("."))
+}
+
+/// Parse an address, or a network as address/prefix-length, and return its
+/// reverse name.  Host bits of a network are ignored.
+pub fn name_for(text: &str) -> Result<String, String> {
+    let (address, prefix) = match text.find('/') {
+        Some(idx) => (&text[..idx], Some(&text[idx + 1..])),
+        None => (text, None),
+    };
+    let ip: IpAddr = address
+        .parse()
+        .map_err(|_| format!("{} is not an IP address", address))?;
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix
+            .parse()
+            .ok()
+            .filter(|p| *p <= max)
+            .ok_or_else(|| format!("{} is not a prefix length for {}", prefix, address))?,
+        None => max,
+    };
+
+    match ip {
+        IpAddr::V4(ip) => Ok(network_v4(ip, prefix)),
+        IpAddr::V6(ip) => network_v6(ip, prefix),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reverse_addresses() {
+        assert_eq!(
+            name_for("93.184.216.34").unwrap(),
+            "34.216.184.93.in-addr.arpa"
+        );
+        assert_eq!(
+            name_for("2001:db8::567:89ab").unwrap(),
+            "b.a.9.8.7.6.5.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+        assert!(name_for("192.0.2").is_err());
+    }
+
+    #[test]
+    fn reverse_networks() {
+        assert_eq!(name_for("192.0.2.0/24").unwrap(), "2.0.192.in-addr.arpa");
+        assert_eq!(
+            name_for("192.0.2.77/26").unwrap(),
+            "64/26.2.0.192.in-addr.arpa"
+        );
+        assert_eq!(name_for("10.16.0.0/12").unwrap(), "16/12.10.in-addr.arpa");
+        assert_eq!(name_for("0.0.0.0/0").unwrap(), "in-addr.arpa");
+        assert_eq!(
+            name_for("2001:db8::/32").unwrap(),
+            "8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+        assert!(name_for("2001:db8::/33").is_err());
+        assert!(name_for("192.0.2.0/33").is_err());
+    }
+}
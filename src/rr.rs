@@ -1,55 +1,123 @@
+use std::cmp::Ordering;
 use std::error;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
+use crate::compress::{Compression, NameCompressor};
 use crate::errors::ParseError;
 use crate::rdata::RData;
-use crate::utils::{byte_combine, bytes_to_name_offset, extract_name};
+use crate::ttl::Ttl;
+use crate::utils::{byte_combine, extract_name, TRUNCATED};
 use std::convert::TryFrom;
 
-#[derive(Debug, Clone, Copy)]
-#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RRType {
-    A = 1,      // a host address
-    NS = 2,     // an authoritative name server
-    MD = 3,     // a mail destination (Obsolete - use MX)
-    MF = 4,     // a mail forwarder (Obsolete - use MX)
-    CNAME = 5,  // the canonical name for an alias
-    SOA = 6,    // marks the start of a zone of authority
-    MB = 7,     // a mailbox domain name (EXPERIMENTAL)
-    MG = 8,     // a mail group member (EXPERIMENTAL)
-    MR = 9,     // a mail rename domain name (EXPERIMENTAL)
-    NULL = 10,  // a null RR (EXPERIMENTAL)
-    WKS = 11,   // a well known service description
-    PTR = 12,   // a domain name pointer
-    HINFO = 13, // host information
-    MINFO = 14, // mailbox or mail list information
-    MX = 15,    // mail exchange
-    TXT = 16,   // text strings
-    AAAA = 28,  // ipv6 host address
-}
-
-impl TryFrom<u16> for RRType {
-    type Error = ParseError;
-    fn try_from(original: u16) -> Result<Self, Self::Error> {
+    A,            // 1 a host address
+    NS,           // 2 an authoritative name server
+    MD,           // 3 a mail destination (Obsolete - use MX)
+    MF,           // 4 a mail forwarder (Obsolete - use MX)
+    CNAME,        // 5 the canonical name for an alias
+    SOA,          // 6 marks the start of a zone of authority
+    MB,           // 7 a mailbox domain name (EXPERIMENTAL)
+    MG,           // 8 a mail group member (EXPERIMENTAL)
+    MR,           // 9 a mail rename domain name (EXPERIMENTAL)
+    NULL,         // 10 a null RR (EXPERIMENTAL)
+    WKS,          // 11 a well known service description
+    PTR,          // 12 a domain name pointer
+    HINFO,        // 13 host information
+    MINFO,        // 14 mailbox or mail list information
+    MX,           // 15 mail exchange
+    TXT,          // 16 text strings
+    AAAA,         // 28 ipv6 host address
+    SRV,          // 33 service location (RFC 2782)
+    NAPTR,        // 35 naming authority pointer (RFC 3403)
+    OPT,          // 41 EDNS pseudo-RR (RFC 6891)
+    SSHFP,        // 44 SSH key fingerprint (RFC 4255)
+    TLSA,         // 52 TLS certificate association (RFC 6698)
+    OPENPGPKEY,   // 61 OpenPGP public key (RFC 7929)
+    SVCB,         // 64 general purpose service binding (RFC 9460)
+    HTTPS,        // 65 service binding for HTTP (RFC 9460)
+    URI,          // 256 uniform resource identifier (RFC 7553)
+    CAA,          // 257 certification authority authorization (RFC 8659)
+    Unknown(u16), // any other type, kept by its code (RFC 3597)
+}
+
+impl From<u16> for RRType {
+    fn from(original: u16) -> Self {
+        match original {
+            1 => RRType::A,
+            2 => RRType::NS,
+            3 => RRType::MD,
+            4 => RRType::MF,
+            5 => RRType::CNAME,
+            6 => RRType::SOA,
+            7 => RRType::MB,
+            8 => RRType::MG,
+            9 => RRType::MR,
+            10 => RRType::NULL,
+            11 => RRType::WKS,
+            12 => RRType::PTR,
+            13 => RRType::HINFO,
+            14 => RRType::MINFO,
+            15 => RRType::MX,
+            16 => RRType::TXT,
+            28 => RRType::AAAA,
+            33 => RRType::SRV,
+            35 => RRType::NAPTR,
+            41 => RRType::OPT,
+            44 => RRType::SSHFP,
+            52 => RRType::TLSA,
+            61 => RRType::OPENPGPKEY,
+            64 => RRType::SVCB,
+            65 => RRType::HTTPS,
+            256 => RRType::URI,
+            257 => RRType::CAA,
+            _ => RRType::Unknown(original),
+        }
+    }
+}
+
+impl From<RRType> for u16 {
+    fn from(original: RRType) -> u16 {
         match original {
-            1 => Ok(RRType::A),
-            2 => Ok(RRType::NS),
-            3 => Ok(RRType::MD),
-            4 => Ok(RRType::MF),
-            5 => Ok(RRType::CNAME),
-            6 => Ok(RRType::SOA),
-            7 => Ok(RRType::MB),
-            8 => Ok(RRType::MG),
-            9 => Ok(RRType::MR),
-            10 => Ok(RRType::NULL),
-            11 => Ok(RRType::WKS),
-            12 => Ok(RRType::PTR),
-            13 => Ok(RRType::HINFO),
-            14 => Ok(RRType::MINFO),
-            15 => Ok(RRType::MX),
-            16 => Ok(RRType::TXT),
-            28 => Ok(RRType::AAAA),
-            _ => Err(ParseError::InvalidRRType),
+            RRType::A => 1,
+            RRType::NS => 2,
+            RRType::MD => 3,
+            RRType::MF => 4,
+            RRType::CNAME => 5,
+            RRType::SOA => 6,
+            RRType::MB => 7,
+            RRType::MG => 8,
+            RRType::MR => 9,
+            RRType::NULL => 10,
+            RRType::WKS => 11,
+            RRType::PTR => 12,
+            RRType::HINFO => 13,
+            RRType::MINFO => 14,
+            RRType::MX => 15,
+            RRType::TXT => 16,
+            RRType::AAAA => 28,
+            RRType::SRV => 33,
+            RRType::NAPTR => 35,
+            RRType::OPT => 41,
+            RRType::SSHFP => 44,
+            RRType::TLSA => 52,
+            RRType::OPENPGPKEY => 61,
+            RRType::SVCB => 64,
+            RRType::HTTPS => 65,
+            RRType::URI => 256,
+            RRType::CAA => 257,
+            RRType::Unknown(code) => code,
+        }
+    }
+}
+
+/// The mnemonic, or TYPE<n> for types without one (RFC 3597 §5).
+impl fmt::Display for RRType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RRType::Unknown(code) => write!(f, "TYPE{}", code),
+            _ => write!(f, "{:?}", self),
         }
     }
 }
@@ -58,34 +126,64 @@ impl TryFrom<String> for RRType {
     type Error = ParseError;
     fn try_from(original: String) -> Result<Self, Self::Error> {
         match original.as_ref() {
-            "A" => Ok(RRType::A),         // 1 a host address
-            "NS" => Ok(RRType::NS),       // 2 an authoritative name server
-            "MD" => Ok(RRType::MD),       // 3 a mail destination (Obsolete - use MX)
-            "MF" => Ok(RRType::MF),       // 4 a mail forwarder (Obsolete - use MX)
-            "CNAME" => Ok(RRType::CNAME), // 5 the canonical name for an alias
-            "SOA" => Ok(RRType::SOA),     // 6 marks the start of a zone of authority
-            "MB" => Ok(RRType::MB),       // 7 a mailbox domain name (EXPERIMENTAL)
-            "MG" => Ok(RRType::MG),       // 8 a mail group member (EXPERIMENTAL)
-            "MR" => Ok(RRType::MR),       // 9 a mail rename domain name (EXPERIMENTAL)
-            "NULL" => Ok(RRType::NULL),   // 10 a null RR (EXPERIMENTAL)
-            "WKS" => Ok(RRType::WKS),     // 11 a well known service description
-            "PTR" => Ok(RRType::PTR),     // 12 a domain name pointer
-            "HINFO" => Ok(RRType::HINFO), // 13 host information
-            "MINFO" => Ok(RRType::MINFO), // 14 mailbox or mail list information
-            "MX" => Ok(RRType::MX),       // 15 mail exchange
-            "TXT" => Ok(RRType::TXT),     // 16 text strings
-            "AAAA" => Ok(RRType::AAAA),   // 28 ipv6 host address
-            _ => Err(ParseError::InvalidRRType),
+            "A" => Ok(RRType::A),                   // 1 a host address
+            "NS" => Ok(RRType::NS),                 // 2 an authoritative name server
+            "MD" => Ok(RRType::MD),                 // 3 a mail destination (Obsolete - use MX)
+            "MF" => Ok(RRType::MF),                 // 4 a mail forwarder (Obsolete - use MX)
+            "CNAME" => Ok(RRType::CNAME),           // 5 the canonical name for an alias
+            "SOA" => Ok(RRType::SOA),               // 6 marks the start of a zone of authority
+            "MB" => Ok(RRType::MB),                 // 7 a mailbox domain name (EXPERIMENTAL)
+            "MG" => Ok(RRType::MG),                 // 8 a mail group member (EXPERIMENTAL)
+            "MR" => Ok(RRType::MR),                 // 9 a mail rename domain name (EXPERIMENTAL)
+            "NULL" => Ok(RRType::NULL),             // 10 a null RR (EXPERIMENTAL)
+            "WKS" => Ok(RRType::WKS),               // 11 a well known service description
+            "PTR" => Ok(RRType::PTR),               // 12 a domain name pointer
+            "HINFO" => Ok(RRType::HINFO),           // 13 host information
+            "MINFO" => Ok(RRType::MINFO),           // 14 mailbox or mail list information
+            "MX" => Ok(RRType::MX),                 // 15 mail exchange
+            "TXT" => Ok(RRType::TXT),               // 16 text strings
+            "AAAA" => Ok(RRType::AAAA),             // 28 ipv6 host address
+            "SRV" => Ok(RRType::SRV),               // 33 service location
+            "NAPTR" => Ok(RRType::NAPTR),           // 35 naming authority pointer
+            "OPT" => Ok(RRType::OPT),               // 41 EDNS pseudo-RR
+            "SSHFP" => Ok(RRType::SSHFP),           // 44 SSH key fingerprint
+            "TLSA" => Ok(RRType::TLSA),             // 52 TLS certificate association
+            "OPENPGPKEY" => Ok(RRType::OPENPGPKEY), // 61 OpenPGP public key
+            "SVCB" => Ok(RRType::SVCB),             // 64 general purpose service binding
+            "HTTPS" => Ok(RRType::HTTPS),           // 65 service binding for HTTP
+            "URI" => Ok(RRType::URI),               // 256 uniform resource identifier
+            "CAA" => Ok(RRType::CAA),               // 257 certification authority authorization
+            _ => match original.strip_prefix("TYPE").map(str::parse::<u16>) {
+                Some(Ok(code)) => Ok(RRType::from(code)),
+                _ => Err(ParseError::InvalidRRType),
+            },
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Class {
     IN = 1,
     CS = 2,
     CH = 3,
     HS = 4,
+    NONE = 254, // RFC 2136, only in UPDATE messages
+    ANY = 255,  // QCLASS only
+}
+
+impl TryFrom<String> for Class {
+    type Error = ParseError;
+    fn try_from(original: String) -> Result<Self, Self::Error> {
+        match original.to_uppercase().as_ref() {
+            "IN" => Ok(Class::IN),
+            "CS" => Ok(Class::CS),
+            "CH" => Ok(Class::CH),
+            "HS" => Ok(Class::HS),
+            "NONE" => Ok(Class::NONE),
+            "ANY" => Ok(Class::ANY),
+            _ => Err(ParseError::InvalidClass),
+        }
+    }
 }
 
 impl TryFrom<u16> for Class {
@@ -96,49 +194,94 @@ impl TryFrom<u16> for Class {
             2 => Ok(Class::CS),
             3 => Ok(Class::CH),
             4 => Ok(Class::HS),
+            254 => Ok(Class::NONE),
+            255 => Ok(Class::ANY),
             _ => Err(ParseError::InvalidClass),
         }
     }
 }
 
-fn extract_ttl(bytes: &[u8], offset: usize) -> i32 {
-    (16 * 16 * (byte_combine(bytes[offset], bytes[offset + 1]) as i32)
-        + (byte_combine(bytes[offset + 2], bytes[offset + 3]) as i32))
+fn extract_ttl(bytes: &[u8], offset: usize) -> Ttl {
+    Ttl::from_wire(u32::from_be_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ]))
+}
+
+/// Compare names in DNSSEC canonical order (RFC 4034 §6.1): label by label
+/// from the root down, each label lowercased and compared as unsigned
+/// octets, so that a name sorts before the names below it.
+pub fn canonical_name_cmp(a: &[String], b: &[String]) -> Ordering {
+    let labels = |name: &[String]| {
+        name.iter()
+            .rev()
+            .filter(|l| !l.is_empty())
+            .map(|l| l.to_ascii_lowercase())
+            .collect::<Vec<_>>()
+    };
+    labels(a).cmp(&labels(b))
 }
 
+#[derive(Debug, Clone)]
 pub struct RR {
     name: Vec<String>,
     rrtype: RRType,
     class: Class,
-    ttl: i32,
+    ttl: Ttl,
     rdlength: u16,
     rdata_parsed: RData,
 }
 
+// RDLENGTH depends on how the names in the RDATA were compressed, so it
+// plays no part in comparing records.
+impl PartialEq for RR {
+    fn eq(&self, other: &RR) -> bool {
+        self.name == other.name
+            && self.rrtype == other.rrtype
+            && self.class == other.class
+            && self.ttl == other.ttl
+            && self.rdata_parsed == other.rdata_parsed
+    }
+}
+
+impl Eq for RR {}
+
+impl Hash for RR {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.rrtype.hash(state);
+        self.class.hash(state);
+        self.ttl.hash(state);
+        self.rdata_parsed.hash(state);
+    }
+}
+
 impl RR {
-    pub fn from_wire(buf: &[u8], mut offset: usize) -> Result<(RR, usize), &dyn error::Error> {
-        let mut name = Vec::new();
-        let name_type = buf[offset] >> 6;
-
-        if name_type == 3 {
-            let name_offset = bytes_to_name_offset(buf[offset], buf[offset + 1]);
-            let (mut ref_name, _loffset) = extract_name(buf, name_offset as usize)?;
-            name.append(&mut ref_name);
-            offset += 2;
-        } else if name_type == 0 {
-            let (mut ref_name, new_offset) = extract_name(buf, offset)?;
-            name.append(&mut ref_name);
-            offset = new_offset;
-        } else {
-            panic!("Unimplemented name type: {:#b}", name_type);
+    pub fn new(name: Vec<String>, rrtype: RRType, class: Class, ttl: Ttl, rdata: RData) -> RR {
+        RR {
+            name: name,
+            rrtype: rrtype,
+            class: class,
+            ttl: ttl,
+            rdlength: rdata.to_wire().len() as u16,
+            rdata_parsed: rdata,
         }
+    }
 
-        // XXX: do we really want to unwrap here?!
-        let rrtype_u16 = byte_combine(buf[offset], buf[offset + 1]);
-        let rrtype = RRType::try_from(rrtype_u16)
-            .expect(&format!("Failed to build RRType from {:?}", rrtype_u16));
+    pub fn from_wire(buf: &[u8], mut offset: usize) -> Result<(RR, usize), &dyn error::Error> {
+        let (name, new_offset) = extract_name(buf, offset)?;
+        offset = new_offset;
+
+        // TYPE, CLASS, TTL and RDLENGTH
+        if buf.len() < offset + 10 {
+            return Err(TRUNCATED);
+        }
+        let rrtype = RRType::from(byte_combine(buf[offset], buf[offset + 1]));
         offset += 2;
-        let class = Class::try_from(byte_combine(buf[offset], buf[offset + 1])).unwrap();
+        let class = Class::try_from(byte_combine(buf[offset], buf[offset + 1]))
+            .map_err(|_| &ParseError::InvalidClass as &dyn error::Error)?;
         offset += 2;
         let ttl = extract_ttl(buf, offset);
         offset += 4;
@@ -159,6 +302,74 @@ impl RR {
         offset += rdlength;
         Ok((rr, offset))
     }
+
+    /// The record on its own, with no names compressed.
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = Vec::new();
+        self.write_wire(&mut wire, &mut NameCompressor::new(Compression::Disabled));
+        wire
+    }
+
+    /// The record in the canonical form used for DNSSEC signing: names in
+    /// full and lowercased (RFC 4034 §6.2).
+    pub fn to_canonical_wire(&self) -> Vec<u8> {
+        let mut wire = Vec::new();
+        self.write_wire(&mut wire, &mut NameCompressor::new(Compression::Canonical));
+        wire
+    }
+
+    /// Compare in DNSSEC canonical order (RFC 4034 §6): by owner name, then
+    /// class and type, then RDATA in canonical form as unsigned octets.
+    /// TTLs are not compared.
+    pub fn canonical_cmp(&self, other: &RR) -> Ordering {
+        canonical_name_cmp(&self.name, &other.name)
+            .then((self.class as u16).cmp(&(other.class as u16)))
+            .then(u16::from(self.rrtype).cmp(&u16::from(other.rrtype)))
+            .then_with(|| {
+                self.rdata_parsed
+                    .to_canonical_wire()
+                    .cmp(&other.rdata_parsed.to_canonical_wire())
+            })
+    }
+
+    /// Append the record to `wire`, which holds the message being written.
+    pub fn write_wire(&self, wire: &mut Vec<u8>, names: &mut NameCompressor) {
+        names.write_name(wire, &self.name);
+        wire.extend_from_slice(&u16::from(self.rrtype).to_be_bytes());
+        wire.extend_from_slice(&(self.class as u16).to_be_bytes());
+        wire.extend_from_slice(&self.ttl.as_secs().to_be_bytes());
+        let rdlength_at = wire.len();
+        wire.extend_from_slice(&[0, 0]);
+        self.rdata_parsed.write_wire(wire, names);
+        let rdlength = (wire.len() - rdlength_at - 2) as u16;
+        wire[rdlength_at..rdlength_at + 2].copy_from_slice(&rdlength.to_be_bytes());
+    }
+
+    pub fn name(&self) -> &[String] {
+        &self.name
+    }
+
+    pub fn rrtype(&self) -> RRType {
+        self.rrtype
+    }
+
+    pub fn class(&self) -> &Class {
+        &self.class
+    }
+
+    pub fn ttl(&self) -> Ttl {
+        self.ttl
+    }
+
+    /// The same record with another TTL.
+    pub fn with_ttl(mut self, ttl: Ttl) -> RR {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn rdata(&self) -> &RData {
+        &self.rdata_parsed
+    }
 }
 
 impl fmt::Display for RR {
@@ -167,10 +378,11 @@ impl fmt::Display for RR {
 
         write!(
             f,
-            "{}\t{:?}\t{:?}\tTTL: {:?}, RDLEN: {:?}\n{}",
+            "{}\t{}\t{:?}\tTTL: {} ({}), RDLEN: {:?}\n{}",
             self.name.join("."),
             self.rrtype,
             self.class,
+            self.ttl.as_secs(),
             self.ttl,
             self.rdlength,
             rdata_fmt
@@ -181,12 +393,84 @@ impl fmt::Display for RR {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::message::Message;
+    use std::collections::HashSet;
 
     #[test]
     fn rrtype_u16_round_trip() {
         let cname: u16 = 5;
-        let rrt = RRType::try_from(cname).unwrap();
-        let _u16 = rrt as u16;
+        let rrt = RRType::from(cname);
+        let _u16 = u16::from(rrt);
         assert_eq!(_u16, cname);
+
+        let rrsig = RRType::from(46);
+        assert_eq!(rrsig, RRType::Unknown(46));
+        assert_eq!(u16::from(rrsig), 46);
+        assert_eq!(rrsig.to_string(), "TYPE46");
+        assert_eq!(RRType::try_from("TYPE46".to_string()).unwrap(), rrsig);
+        assert_eq!(RRType::try_from("TYPE5".to_string()).unwrap(), RRType::CNAME);
+    }
+
+    #[test]
+    fn canonical_name_order() {
+        // RFC 4034 §6.1
+        let ordered: Vec<Vec<String>> = [
+            "example",
+            "a.example",
+            "yljkjljk.a.example",
+            "Z.a.example",
+            "zABC.a.EXAMPLE",
+            "z.example",
+            "\u{1}.z.example",
+            "*.z.example",
+        ]
+        .iter()
+        .map(|name| name.split('.').map(String::from).collect())
+        .collect();
+        for pair in ordered.windows(2) {
+            assert_eq!(canonical_name_cmp(&pair[0], &pair[1]), Ordering::Less);
+        }
+        let root: Vec<String> = vec!["".to_string()];
+        assert_eq!(canonical_name_cmp(&root, &ordered[0]), Ordering::Less);
+        let lower: Vec<String> = ["zabc", "a", "example"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        assert_eq!(canonical_name_cmp(&ordered[4], &lower), Ordering::Equal);
+    }
+
+    #[test]
+    fn rr_round_trip() {
+        let tokens = vec!["x86_64".to_string(), "Linux".to_string()];
+        let rdata = RData::from_presentation(RRType::HINFO, &tokens, &[]).unwrap();
+        let name = vec!["host".to_string(), "example".to_string()];
+        let rr = RR::new(name, RRType::HINFO, Class::IN, Ttl::from_secs(86400), rdata);
+
+        let wire = rr.to_wire();
+        let (parsed, offset) = RR::from_wire(&wire, 0).unwrap();
+        assert_eq!(offset, wire.len());
+        assert_eq!(parsed, rr);
+        assert_eq!(parsed.to_wire(), wire);
+    }
+
+    #[test]
+    fn rr_equality_ignores_compression() {
+        let labels = |name: &str| name.split('.').map(String::from).collect::<Vec<_>>();
+        let tokens = vec!["10".to_string(), "mail.example.com.".to_string()];
+        let mx = RData::from_presentation(RRType::MX, &tokens, &[]).unwrap();
+        let rr = RR::new(labels("example.com"), RRType::MX, Class::IN, Ttl::from_secs(300), mx);
+        let mut reply = Message::new(labels("example.com"), "MX".to_string());
+        reply.answer.push(rr.clone());
+        reply.ancount = 1;
+
+        let answer = |wire: Vec<u8>| Message::from_wire(&wire).unwrap().answer.remove(0);
+        let compressed = answer(reply.to_wire());
+        let full = answer(reply.to_wire_with(Compression::Disabled));
+        assert_ne!(compressed.rdlength, full.rdlength);
+        assert_eq!(compressed, full);
+        assert_eq!(compressed, rr);
+
+        let set: HashSet<RR> = vec![compressed, full, rr].into_iter().collect();
+        assert_eq!(set.len(), 1);
     }
 }
@@ -0,0 +1,130 @@
+/* Records that share an owner name, class and type (RFC 2181 §5): the unit
+ * that DNSSEC signs and that answers are made of. */
+
+use std::cmp::Ordering;
+
+use crate::rr::{canonical_name_cmp, Class, RRType, RR};
+use crate::ttl::Ttl;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RRset {
+    /// In canonical order, without duplicates.
+    records: Vec<RR>,
+}
+
+impl RRset {
+    pub fn new(rr: RR) -> RRset {
+        RRset { records: vec![rr] }
+    }
+
+    /// Group records into RRsets, in the order each set first appears.
+    pub fn group(records: Vec<RR>) -> Vec<RRset> {
+        let mut rrsets: Vec<RRset> = Vec::new();
+        for rr in records {
+            match rrsets.iter_mut().find(|rrset| rrset.matches(&rr)) {
+                Some(rrset) => {
+                    rrset.insert(rr);
+                }
+                None => rrsets.push(RRset::new(rr)),
+            }
+        }
+        rrsets
+    }
+
+    /// Does `rr` belong in this set?  Owner names compare without regard
+    /// to case.
+    pub fn matches(&self, rr: &RR) -> bool {
+        let first = &self.records[0];
+        canonical_name_cmp(first.name(), rr.name()) == Ordering::Equal
+            && *first.class() as u16 == *rr.class() as u16
+            && first.rrtype() == rr.rrtype()
+    }
+
+    /// Add a record to the set, keeping canonical order; one that only
+    /// differs from a member in TTL or case is a duplicate and is dropped.
+    /// Returns false if the record does not belong in the set.
+    pub fn insert(&mut self, rr: RR) -> bool {
+        if !self.matches(&rr) {
+            return false;
+        }
+        if let Err(at) = self
+            .records
+            .binary_search_by(|member| member.canonical_cmp(&rr))
+        {
+            self.records.insert(at, rr);
+        }
+        true
+    }
+
+    pub fn name(&self) -> &[String] {
+        self.records[0].name()
+    }
+
+    pub fn class(&self) -> Class {
+        *self.records[0].class()
+    }
+
+    pub fn rrtype(&self) -> RRType {
+        self.records[0].rrtype()
+    }
+
+    /// The lowest TTL of the records; they should all be the same
+    /// (RFC 2181 §5.2).
+    pub fn ttl(&self) -> Ttl {
+        self.records.iter().map(RR::ttl).min().unwrap_or_default()
+    }
+
+    pub fn records(&self) -> &[RR] {
+        &self.records
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zone;
+
+    #[test]
+    fn rrset_group() {
+        let records = zone::parse_str(
+            "$TTL 300\n\
+             $ORIGIN example.\n\
+             @ NS b.ns\n\
+             www 60 A 192.0.2.2\n\
+             @ NS a.ns\n\
+             WWW A 192.0.2.1\n\
+             @ NS A.NS\n\
+             www AAAA 2001:db8::1\n",
+            ".",
+        )
+        .unwrap();
+        let rrsets = RRset::group(records.clone());
+        assert_eq!(rrsets.len(), 3);
+
+        let ns = &rrsets[0];
+        assert_eq!(ns.rrtype(), RRType::NS);
+        let targets: Vec<String> = ns
+            .records()
+            .iter()
+            .map(|rr| rr.rdata().to_presentation())
+            .collect();
+        assert_eq!(targets, vec!["a.ns.example.", "b.ns.example."]);
+
+        let a = &rrsets[1];
+        assert_eq!(a.records(), &[records[3].clone(), records[1].clone()][..]);
+        assert_eq!(a.ttl(), Ttl::from_secs(60));
+        assert_eq!(rrsets[2].len(), 1);
+
+        let mut aaaa = rrsets[2].clone();
+        assert!(!aaaa.insert(records[0].clone()));
+        assert_eq!(aaaa, rrsets[2]);
+    }
+}
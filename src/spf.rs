@@ -0,0 +1,704 @@
+/* Sender Policy Framework (RFC 7208): fetch a domain's SPF record and run
+ * check_host() for a sender IP, keeping a trace of how the result was
+ * reached. */
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::message::RCode;
+use crate::rdata::RData;
+use crate::resolver::Resolver;
+use crate::reverse::reverse_name;
+use crate::rr::{RRType, RR};
+use crate::utils::{fqdn, ipv6_nibbles};
+
+/// Mechanisms and modifiers that query DNS may do so at most this many
+/// times per evaluation (§4.6.4).
+const LOOKUP_LIMIT: usize = 10;
+/// Lookups answering with no records or NXDOMAIN (§4.6.4).
+const VOID_LOOKUP_LIMIT: usize = 2;
+/// Names considered per "mx" or "ptr" mechanism (§4.6.4).
+const NAME_LIMIT: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpfResult {
+    None,
+    Neutral,
+    Pass,
+    Fail,
+    SoftFail,
+    TempError,
+    PermError,
+}
+
+impl fmt::Display for SpfResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SpfResult::None => "none",
+            SpfResult::Neutral => "neutral",
+            SpfResult::Pass => "pass",
+            SpfResult::Fail => "fail",
+            SpfResult::SoftFail => "softfail",
+            SpfResult::TempError => "temperror",
+            SpfResult::PermError => "permerror",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub struct Evaluation {
+    pub result: SpfResult,
+    /// DNS lookups counted against the limit of 10.
+    pub lookups: usize,
+    /// One line per record fetched and term evaluated, indented by
+    /// include/redirect depth.
+    pub trace: Vec<String>,
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in self.trace.iter() {
+            writeln!(f, "{}", line)?;
+        }
+        write!(f, "result: {} ({} DNS lookups)", self.result, self.lookups)
+    }
+}
+
+enum Mechanism {
+    All,
+    Include(String),
+    A(Option<String>, u8, u8),
+    Mx(Option<String>, u8, u8),
+    Ptr(Option<String>),
+    Ip4(Ipv4Addr, u8),
+    Ip6(Ipv6Addr, u8),
+    Exists(String),
+}
+
+enum Term<'a> {
+    Directive(SpfResult, Mechanism, &'a str),
+    Redirect(String),
+    /// exp= and unknown modifiers, which do not affect the result.
+    Ignored,
+}
+
+/// Split "name:domain/cidr//cidr6" style arguments for a and mx.
+fn parse_dual_cidr(rest: &str) -> Option<(Option<String>, u8, u8)> {
+    let (domain, cidrs) = match rest.find('/') {
+        Some(idx) => (&rest[..idx], &rest[idx..]),
+        None => (rest, ""),
+    };
+    let domain = match domain.strip_prefix(':') {
+        Some("") => return None,
+        Some(domain) => Some(domain.to_string()),
+        None if domain.is_empty() => None,
+        None => return None,
+    };
+
+    let (mut v4, mut v6) = (32, 128);
+    let (v4_text, v6_text) = match cidrs.find("//") {
+        Some(idx) => (&cidrs[..idx], Some(&cidrs[idx + 2..])),
+        None => (cidrs, None),
+    };
+    if let Some(bits) = v4_text.strip_prefix('/') {
+        v4 = bits.parse().ok().filter(|b| *b <= 32)?;
+    } else if !v4_text.is_empty() {
+        return None;
+    }
+    if let Some(bits) = v6_text {
+        v6 = bits.parse().ok().filter(|b| *b <= 128)?;
+    }
+    Some((domain, v4, v6))
+}
+
+fn parse_network<T: std::str::FromStr>(rest: &str, max: u8) -> Option<(T, u8)> {
+    let rest = rest.strip_prefix(':')?;
+    match rest.find('/') {
+        Some(idx) => {
+            let bits = rest[idx + 1..].parse().ok().filter(|b| *b <= max)?;
+            Some((rest[..idx].parse().ok()?, bits))
+        }
+        None => Some((rest.parse().ok()?, max)),
+    }
+}
+
+fn parse_term(term: &str) -> Option<Term<'_>> {
+    let name_end = term.find([':', '/', '=']).unwrap_or(term.len());
+    if term[name_end..].starts_with('=') {
+        let value = &term[name_end + 1..];
+        return match term[..name_end].to_lowercase().as_str() {
+            "redirect" => Some(Term::Redirect(value.to_string())),
+            _ => Some(Term::Ignored),
+        };
+    }
+
+    let (qualifier, name) = match term.chars().next()? {
+        '+' => (SpfResult::Pass, &term[1..name_end]),
+        '-' => (SpfResult::Fail, &term[1..name_end]),
+        '~' => (SpfResult::SoftFail, &term[1..name_end]),
+        '?' => (SpfResult::Neutral, &term[1..name_end]),
+        _ => (SpfResult::Pass, &term[..name_end]),
+    };
+    let rest = &term[name_end..];
+    let domain_spec = || rest.strip_prefix(':').filter(|d| !d.is_empty());
+
+    let mechanism = match name.to_lowercase().as_str() {
+        "all" if rest.is_empty() => Mechanism::All,
+        "include" => Mechanism::Include(domain_spec()?.to_string()),
+        "exists" => Mechanism::Exists(domain_spec()?.to_string()),
+        "a" => {
+            let (domain, v4, v6) = parse_dual_cidr(rest)?;
+            Mechanism::A(domain, v4, v6)
+        }
+        "mx" => {
+            let (domain, v4, v6) = parse_dual_cidr(rest)?;
+            Mechanism::Mx(domain, v4, v6)
+        }
+        "ptr" if rest.is_empty() => Mechanism::Ptr(None),
+        "ptr" => Mechanism::Ptr(Some(domain_spec()?.to_string())),
+        "ip4" => {
+            let (addr, bits) = parse_network(rest, 32)?;
+            Mechanism::Ip4(addr, bits)
+        }
+        "ip6" => {
+            let (addr, bits) = parse_network(rest, 128)?;
+            Mechanism::Ip6(addr, bits)
+        }
+        _ => return None,
+    };
+    Some(Term::Directive(qualifier, mechanism, term))
+}
+
+fn in_network(ip: IpAddr, network: IpAddr, bits: u8) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - bits as u32).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - bits as u32).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+fn is_spf_record(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    lower == "v=spf1" || lower.starts_with("v=spf1 ")
+}
+
+struct Checker<'a, R: Resolver> {
+    resolver: &'a R,
+    ip: IpAddr,
+    sender: String,
+    lookups: usize,
+    void_lookups: usize,
+    trace: Vec<String>,
+}
+
+impl<'a, R: Resolver> Checker<'a, R> {
+    fn note(&mut self, depth: usize, text: String) {
+        self.trace.push(format!("{}{}", "  ".repeat(depth), text));
+    }
+
+    fn count_lookup(&mut self, depth: usize) -> Result<(), SpfResult> {
+        self.lookups += 1;
+        if self.lookups > LOOKUP_LIMIT {
+            self.note(depth, format!("more than {} DNS lookups", LOOKUP_LIMIT));
+            return Err(SpfResult::PermError);
+        }
+        Ok(())
+    }
+
+    /// Query for records of one type, counting void lookups; NXDOMAIN is
+    /// an empty answer.
+    fn query(&mut self, name: &str, rrtype: RRType, depth: usize) -> Result<Vec<RR>, SpfResult> {
+        let reply = match self.resolver.resolve(name, rrtype) {
+            Ok(reply) => reply,
+            Err(e) => {
+                self.note(depth, format!("{} {} lookup failed: {}", name, rrtype, e));
+                return Err(SpfResult::TempError);
+            }
+        };
+        match reply.meta.rcode {
+            RCode::NoError | RCode::NameError => (),
+            _ => {
+                self.note(depth, format!("{} {} lookup failed", name, rrtype));
+                return Err(SpfResult::TempError);
+            }
+        }
+
+        let records: Vec<RR> = reply
+            .answer
+            .into_iter()
+            .filter(|rr| rr.rrtype() == rrtype)
+            .collect();
+        if records.is_empty() {
+            self.void_lookups += 1;
+            if self.void_lookups > VOID_LOOKUP_LIMIT {
+                self.note(
+                    depth,
+                    format!("more than {} void lookups", VOID_LOOKUP_LIMIT),
+                );
+                return Err(SpfResult::PermError);
+            }
+        }
+        Ok(records)
+    }
+
+    /// The addresses of `name` in the family of the sender IP.
+    fn addresses(&mut self, name: &str, depth: usize) -> Result<Vec<IpAddr>, SpfResult> {
+        let rrtype = match self.ip {
+            IpAddr::V4(_) => RRType::A,
+            IpAddr::V6(_) => RRType::AAAA,
+        };
+        let records = self.query(name, rrtype, depth)?;
+        Ok(records
+            .iter()
+            .filter_map(|rr| match rr.rdata() {
+                RData::A(data) => Some(IpAddr::V4(data.address())),
+                RData::AAAA(data) => Some(IpAddr::V6(data.address())),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn fetch_record(&mut self, domain: &str, depth: usize) -> Result<String, SpfResult> {
+        let reply = match self.resolver.resolve(domain, RRType::TXT) {
+            Ok(reply) => reply,
+            Err(e) => {
+                self.note(depth, format!("{}: TXT lookup failed: {}", domain, e));
+                return Err(SpfResult::TempError);
+            }
+        };
+        match reply.meta.rcode {
+            RCode::NoError => (),
+            RCode::NameError => {
+                self.note(depth, format!("{}: no such domain", domain));
+                return Err(SpfResult::None);
+            }
+            _ => {
+                self.note(depth, format!("{}: TXT lookup failed", domain));
+                return Err(SpfResult::TempError);
+            }
+        }
+
+        let mut records: Vec<String> = reply
+            .answer
+            .iter()
+            .filter_map(|rr| match rr.rdata() {
+                RData::TXT(data) => Some(data.joined_text()),
+                _ => None,
+            })
+            .filter(|text| is_spf_record(text))
+            .collect();
+        match records.len() {
+            0 => {
+                self.note(depth, format!("{}: no SPF record", domain));
+                Err(SpfResult::None)
+            }
+            1 => Ok(records.remove(0)),
+            n => {
+                self.note(depth, format!("{}: {} SPF records", domain, n));
+                Err(SpfResult::PermError)
+            }
+        }
+    }
+
+    fn check_host(&mut self, domain: &str, depth: usize) -> SpfResult {
+        let record = match self.fetch_record(domain, depth) {
+            Ok(record) => record,
+            Err(result) => return result,
+        };
+        self.note(depth, format!("{}: {}", domain, record));
+        match self.evaluate(&record, domain, depth + 1) {
+            Ok(result) | Err(result) => result,
+        }
+    }
+
+    fn evaluate(
+        &mut self,
+        record: &str,
+        domain: &str,
+        depth: usize,
+    ) -> Result<SpfResult, SpfResult> {
+        // the whole record is checked for syntax before anything is evaluated
+        let mut terms = Vec::new();
+        let mut redirect = None;
+        for text in record.split(' ').filter(|t| !t.is_empty()).skip(1) {
+            match parse_term(text) {
+                Some(Term::Redirect(target)) => {
+                    if redirect.replace(target).is_some() {
+                        self.note(depth, "more than one redirect modifier".to_string());
+                        return Err(SpfResult::PermError);
+                    }
+                }
+                Some(Term::Ignored) => (),
+                Some(directive) => terms.push(directive),
+                None => {
+                    self.note(depth, format!("{}: invalid term", text));
+                    return Err(SpfResult::PermError);
+                }
+            }
+        }
+
+        for term in terms.iter() {
+            if let Term::Directive(qualifier, mechanism, text) = term {
+                if self.matches(mechanism, text, domain, depth)? {
+                    self.note(depth, format!("{}: match -> {}", text, qualifier));
+                    return Ok(*qualifier);
+                }
+                self.note(depth, format!("{}: no match", text));
+            }
+        }
+
+        if let Some(target) = redirect {
+            self.count_lookup(depth)?;
+            let target = self.expand(&target, domain, depth)?;
+            self.note(depth, format!("redirect={}", target));
+            return match self.check_host(&target, depth + 1) {
+                SpfResult::None => Err(SpfResult::PermError),
+                result => Ok(result),
+            };
+        }
+        Ok(SpfResult::Neutral)
+    }
+
+    fn target(
+        &mut self,
+        spec: &Option<String>,
+        domain: &str,
+        depth: usize,
+    ) -> Result<String, SpfResult> {
+        match spec {
+            Some(spec) => self.expand(spec, domain, depth),
+            None => Ok(domain.to_string()),
+        }
+    }
+
+    fn matches(
+        &mut self,
+        mechanism: &Mechanism,
+        text: &str,
+        domain: &str,
+        depth: usize,
+    ) -> Result<bool, SpfResult> {
+        match mechanism {
+            Mechanism::All => Ok(true),
+            Mechanism::Ip4(network, bits) => Ok(in_network(self.ip, IpAddr::V4(*network), *bits)),
+            Mechanism::Ip6(network, bits) => Ok(in_network(self.ip, IpAddr::V6(*network), *bits)),
+            Mechanism::Include(spec) => {
+                self.count_lookup(depth)?;
+                let target = self.expand(spec, domain, depth)?;
+                match self.check_host(&target, depth + 1) {
+                    SpfResult::Pass => Ok(true),
+                    SpfResult::Fail | SpfResult::SoftFail | SpfResult::Neutral => Ok(false),
+                    SpfResult::TempError => Err(SpfResult::TempError),
+                    SpfResult::None | SpfResult::PermError => {
+                        self.note(
+                            depth,
+                            format!("{}: included domain has no usable policy", text),
+                        );
+                        Err(SpfResult::PermError)
+                    }
+                }
+            }
+            Mechanism::A(spec, v4, v6) => {
+                self.count_lookup(depth)?;
+                let target = self.target(spec, domain, depth)?;
+                let bits = if self.ip.is_ipv4() { *v4 } else { *v6 };
+                let ip = self.ip;
+                Ok(self
+                    .addresses(&target, depth)?
+                    .into_iter()
+                    .any(|addr| in_network(ip, addr, bits)))
+            }
+            Mechanism::Mx(spec, v4, v6) => {
+                self.count_lookup(depth)?;
+                let target = self.target(spec, domain, depth)?;
+                let exchanges: Vec<String> = self
+                    .query(&target, RRType::MX, depth)?
+                    .iter()
+                    .filter_map(|rr| match rr.rdata() {
+                        RData::MX(data) => Some(fqdn(data.exchange())),
+                        _ => None,
+                    })
+                    .collect();
+                if exchanges.len() > NAME_LIMIT {
+                    self.note(
+                        depth,
+                        format!("{}: more than {} MX records", text, NAME_LIMIT),
+                    );
+                    return Err(SpfResult::PermError);
+                }
+                let bits = if self.ip.is_ipv4() { *v4 } else { *v6 };
+                for exchange in exchanges.iter() {
+                    let ip = self.ip;
+                    if self
+                        .addresses(exchange, depth)?
+                        .into_iter()
+                        .any(|addr| in_network(ip, addr, bits))
+                    {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            Mechanism::Ptr(spec) => {
+                self.count_lookup(depth)?;
+                let target = self.target(spec, domain, depth)?.to_lowercase();
+                let target = target.trim_end_matches('.');
+                let names: Vec<String> = self
+                    .query(&reverse_name(self.ip), RRType::PTR, depth)?
+                    .iter()
+                    .filter_map(|rr| match rr.rdata() {
+                        RData::PTR(data) => Some(fqdn(data.ptrdname())),
+                        _ => None,
+                    })
+                    .take(NAME_LIMIT)
+                    .collect();
+                for name in names.iter() {
+                    // only names that resolve back to the sender count
+                    let ip = self.ip;
+                    let validated = self.addresses(name, depth)?.contains(&ip);
+                    let name = name.trim_end_matches('.').to_lowercase();
+                    if validated && (name == target || name.ends_with(&format!(".{}", target))) {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            Mechanism::Exists(spec) => {
+                self.count_lookup(depth)?;
+                let target = self.expand(spec, domain, depth)?;
+                Ok(!self.query(&target, RRType::A, depth)?.is_empty())
+            }
+        }
+    }
+
+    /// Expand the macros of a domain-spec (§7).
+    fn expand(&mut self, spec: &str, domain: &str, depth: usize) -> Result<String, SpfResult> {
+        match expand_macros(spec, &self.sender, domain, self.ip) {
+            Some(expanded) => Ok(expanded),
+            None => {
+                self.note(depth, format!("{}: invalid macro", spec));
+                Err(SpfResult::PermError)
+            }
+        }
+    }
+}
+
+fn macro_value(letter: char, sender: &str, domain: &str, ip: IpAddr) -> Option<String> {
+    let (local, sender_domain) = match sender.rfind('@') {
+        Some(idx) => (&sender[..idx], &sender[idx + 1..]),
+        None => ("postmaster", sender),
+    };
+    let value = match letter.to_ascii_lowercase() {
+        's' => sender.to_string(),
+        'l' => local.to_string(),
+        'o' => sender_domain.to_string(),
+        'd' => domain.to_string(),
+        'i' => match ip {
+            IpAddr::V4(ip) => ip.to_string(),
+            IpAddr::V6(ip) => ipv6_nibbles(ip).join("."),
+        },
+        'c' => ip.to_string(),
+        'v' => match ip {
+            IpAddr::V4(_) => "in-addr".to_string(),
+            IpAddr::V6(_) => "ip6".to_string(),
+        },
+        // no HELO or validated client name is known here
+        'h' => sender_domain.to_string(),
+        'p' | 'r' => "unknown".to_string(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Apply the digits, "r" and delimiters of a macro to its value.
+fn transform(value: &str, transformers: &str) -> Option<String> {
+    let digits: String = transformers
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    let mut rest = &transformers[digits.len()..];
+    let reverse = rest.starts_with('r') || rest.starts_with('R');
+    if reverse {
+        rest = &rest[1..];
+    }
+    if !rest.chars().all(|c| ".-+,/_=".contains(c)) {
+        return None;
+    }
+    let delimiters = if rest.is_empty() { "." } else { rest };
+
+    let mut parts: Vec<&str> = value.split(|c| delimiters.contains(c)).collect();
+    if reverse {
+        parts.reverse();
+    }
+    if !digits.is_empty() {
+        let keep: usize = digits.parse().ok().filter(|n| *n > 0)?;
+        if keep < parts.len() {
+            parts = parts.split_off(parts.len() - keep);
+        }
+    }
+    Some(parts.join("."))
+}
+
+fn expand_macros(spec: &str, sender: &str, domain: &str, ip: IpAddr) -> Option<String> {
+    let mut expanded = String::new();
+    let mut chars = spec.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '%' => expanded.push('%'),
+            '_' => expanded.push(' '),
+            '-' => expanded.push_str("%20"),
+            '{' => {
+                let mut body = String::new();
+                // an unterminated macro is a syntax error (§7.1)
+                loop {
+                    match chars.next()? {
+                        '}' => break,
+                        c => body.push(c),
+                    }
+                }
+                let letter = body.chars().next()?;
+                let value = macro_value(letter, sender, domain, ip)?;
+                expanded.push_str(&transform(&value, &body[letter.len_utf8()..])?);
+            }
+            _ => return None,
+        }
+    }
+    Some(expanded)
+}
+
+/// Evaluate the SPF policy of `domain` for mail from `sender` arriving
+/// from `ip`.
+pub fn check_host<R: Resolver>(resolver: &R, ip: IpAddr, domain: &str, sender: &str) -> Evaluation {
+    let domain = domain.trim_end_matches('.');
+    let sender = if sender.is_empty() {
+        format!("postmaster@{}", domain)
+    } else {
+        sender.to_string()
+    };
+    let mut checker = Checker {
+        resolver,
+        ip,
+        sender,
+        lookups: 0,
+        void_lookups: 0,
+        trace: Vec::new(),
+    };
+    let result = checker.check_host(domain, 0);
+    Evaluation {
+        result,
+        lookups: checker.lookups,
+        trace: checker.trace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::ZoneResolver;
+
+    const ZONE: &str = "$ORIGIN example.\n\
+        $TTL 300\n\
+        @ TXT \"v=spf1 include:_spf.example.net ip4:192.0.2.0/24 mx -all\"\n\
+        @ MX 10 mail\n\
+        mail A 198.51.100.25\n\
+        mail AAAA 2001:db8::25\n\
+        _spf.example.net. TXT \"v=spf1 ip4:203.0.113.0/24 ~all\"\n\
+        redirected TXT \"v=spf1 redirect=example.\"\n\
+        looping TXT \"v=spf1 include:looping.example. -all\"\n\
+        macro TXT \"v=spf1 exists:%{ir}.%{v}._spf.%{d} -all\"\n\
+        1.2.0.192.in-addr._spf.macro A 127.0.0.2\n\
+        split TXT \"v=spf1 ip4:192.0.2.1\" \" -all\"\n\
+        double TXT \"v=spf1 -all\"\n\
+        double TXT \"v=spf1 +all\"\n\
+        bad TXT \"v=spf1 ip4:192.0.2.300 -all\"\n";
+
+    fn check(ip: &str, domain: &str) -> Evaluation {
+        let resolver = ZoneResolver { zone: ZONE };
+        check_host(&resolver, ip.parse().unwrap(), domain, "")
+    }
+
+    #[test]
+    fn spf_results() {
+        assert_eq!(check("192.0.2.7", "example").result, SpfResult::Pass);
+        assert_eq!(check("203.0.113.9", "example").result, SpfResult::Pass);
+        assert_eq!(check("198.51.100.25", "example").result, SpfResult::Pass);
+        assert_eq!(check("2001:db8::25", "example").result, SpfResult::Pass);
+        assert_eq!(check("198.51.100.26", "example").result, SpfResult::Fail);
+        assert_eq!(
+            check("198.51.100.26", "redirected.example").result,
+            SpfResult::Fail
+        );
+        assert_eq!(check("192.0.2.1", "split.example").result, SpfResult::Pass);
+        assert_eq!(check("192.0.2.1", "nowhere").result, SpfResult::None);
+        assert_eq!(
+            check("192.0.2.1", "double.example").result,
+            SpfResult::PermError
+        );
+        assert_eq!(
+            check("192.0.2.1", "bad.example").result,
+            SpfResult::PermError
+        );
+    }
+
+    #[test]
+    fn spf_lookup_limit() {
+        let evaluation = check("192.0.2.1", "looping.example");
+        assert_eq!(evaluation.result, SpfResult::PermError);
+        assert_eq!(evaluation.lookups, LOOKUP_LIMIT + 1);
+    }
+
+    #[test]
+    fn spf_macros() {
+        assert_eq!(check("192.0.2.1", "macro.example").result, SpfResult::Pass);
+        assert_eq!(check("192.0.2.2", "macro.example").result, SpfResult::Fail);
+
+        let ip = "2001:db8::cb01".parse().unwrap();
+        assert_eq!(
+            expand_macros("%{ir}.%{v}._spf.%{d2}", "strong-bad@email.example.com", "email.example.com", ip),
+            Some(
+                "1.0.b.c.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6._spf.example.com"
+                    .to_string()
+            )
+        );
+        let ip = "192.0.2.3".parse().unwrap();
+        assert_eq!(
+            expand_macros("%{l1r-}.%{o}", "strong-bad@email.example.com", "", ip),
+            Some("strong.email.example.com".to_string())
+        );
+        assert_eq!(expand_macros("%{z}", "", "", ip), None);
+        assert_eq!(expand_macros("%{d", "", "example.com", ip), None);
+    }
+
+    #[test]
+    fn spf_unterminated_macro() {
+        let zone = "$ORIGIN example.\n@ 300 TXT \"v=spf1 a:%{d -all\"\n";
+        let resolver = ZoneResolver { zone };
+        let evaluation = check_host(&resolver, "192.0.2.1".parse().unwrap(), "example", "");
+        assert_eq!(evaluation.result, SpfResult::PermError);
+    }
+
+    #[test]
+    fn spf_trace() {
+        let evaluation = check("203.0.113.9", "example");
+        assert_eq!(
+            evaluation.trace,
+            vec![
+                "example: v=spf1 include:_spf.example.net ip4:192.0.2.0/24 mx -all",
+                "    _spf.example.net: v=spf1 ip4:203.0.113.0/24 ~all",
+                "      ip4:203.0.113.0/24: match -> pass",
+                "  include:_spf.example.net: match -> pass",
+            ]
+        );
+        assert_eq!(evaluation.lookups, 1);
+    }
+}
@@ -0,0 +1,227 @@
+/* Many outstanding queries over one UDP socket.  A reader thread hands each
+ * reply to the query that is waiting for it, matched on transaction ID,
+ * server address and question; anything else that arrives is dropped. */
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use rand::random;
+
+use crate::message::{self, Message};
+use crate::response::{Response, Transport};
+
+/// How often the reader thread checks whether the engine is gone.
+const READER_POLL: Duration = Duration::from_millis(100);
+/// Length of the DNS header, after which the question section starts.
+const HEADER_LEN: usize = 12;
+
+struct Pending {
+    server: SocketAddr,
+    /// The question section of the query, as sent.
+    question: Vec<u8>,
+    reply: mpsc::Sender<Vec<u8>>,
+}
+
+type PendingMap = Arc<Mutex<HashMap<u16, Pending>>>;
+
+pub struct Engine {
+    socket: Arc<UdpSocket>,
+    pending: PendingMap,
+    timeout: Duration,
+    stop: Arc<AtomicBool>,
+}
+
+/// Does the reply carry the question that was asked?  Names compare
+/// without regard to case.
+fn same_question(reply: &[u8], question: &[u8]) -> bool {
+    match reply.get(HEADER_LEN..HEADER_LEN + question.len()) {
+        Some(asked) => asked.eq_ignore_ascii_case(question),
+        None => false,
+    }
+}
+
+fn read_replies(socket: Arc<UdpSocket>, pending: PendingMap, stop: Arc<AtomicBool>) {
+    let mut buf = [0u8; message::DNS_MSG_MAX];
+    while !stop.load(Ordering::Relaxed) {
+        let (received, from) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(_) => continue,
+        };
+        if received < HEADER_LEN {
+            continue;
+        }
+        let id = u16::from_be_bytes([buf[0], buf[1]]);
+        let mut pending = pending.lock().unwrap();
+        let matches = match pending.get(&id) {
+            Some(waiting) => {
+                waiting.server == from && same_question(&buf[..received], &waiting.question)
+            }
+            None => false,
+        };
+        if matches {
+            let waiting = pending.remove(&id).unwrap();
+            // the query may have timed out in the meantime
+            let _ = waiting.reply.send(buf[..received].to_vec());
+        }
+    }
+}
+
+impl Engine {
+    /// Bind a socket for queries that time out after `timeout`.
+    pub fn new(timeout: Duration) -> io::Result<Engine> {
+        let socket = Arc::new(UdpSocket::bind("0.0.0.0:0")?);
+        socket.set_read_timeout(Some(READER_POLL))?;
+        let pending: PendingMap = Arc::new(Mutex::new(HashMap::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let (reader_socket, reader_pending, reader_stop) =
+            (Arc::clone(&socket), Arc::clone(&pending), Arc::clone(&stop));
+        thread::spawn(move || read_replies(reader_socket, reader_pending, reader_stop));
+
+        Ok(Engine {
+            socket,
+            pending,
+            timeout,
+            stop,
+        })
+    }
+
+    /// Send a query and wait for its reply, returned as received.  Safe to
+    /// call from many threads at once.
+    pub fn exchange(&self, name: String, qtype: String, server: &str) -> io::Result<Vec<u8>> {
+        self.exchange_message(crate::new_query(name, qtype), server)
+    }
+
+    /// Send a prepared query as `exchange` does; its ID is changed if
+    /// another outstanding query has it.
+    pub fn exchange_message(&self, mut query: Message, server: &str) -> io::Result<Vec<u8>> {
+        let server = server
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no server address"))?;
+        let (sender, receiver) = mpsc::channel();
+
+        let wire = {
+            let mut pending = self.pending.lock().unwrap();
+            while pending.contains_key(&query.id) {
+                query.id = random::<u16>();
+            }
+            let wire = query.to_wire();
+            // the OPT record, if any, follows the question
+            let opt_len = query.edns.as_ref().map_or(0, |edns| edns.to_wire().len());
+            pending.insert(
+                query.id,
+                Pending {
+                    server,
+                    question: wire[HEADER_LEN..wire.len() - opt_len].to_vec(),
+                    reply: sender,
+                },
+            );
+            wire
+        };
+
+        if let Err(e) = self.socket.send_to(&wire, server) {
+            self.pending.lock().unwrap().remove(&query.id);
+            return Err(e);
+        }
+        match receiver.recv_timeout(self.timeout) {
+            Ok(reply) => Ok(reply),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&query.id);
+                Err(io::Error::new(io::ErrorKind::TimedOut, "query timed out"))
+            }
+        }
+    }
+
+    /// Send a query and parse its reply.
+    pub fn lookup(&self, name: String, qtype: String, server: &str) -> io::Result<Response> {
+        self.lookup_message(crate::new_query(name, qtype), server)
+    }
+
+    /// Send a prepared query and parse its reply.
+    pub fn lookup_message(&self, query: Message, server: &str) -> io::Result<Response> {
+        let when = SystemTime::now();
+        let started = Instant::now();
+        let reply = self.exchange_message(query, server)?;
+        let elapsed = started.elapsed();
+        Response::new(&reply, server.to_string(), Transport::Udp, when, elapsed)
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A server that collects `count` queries, then answers them in reverse
+    /// order, first sending a reply to each with the question altered.
+    fn reversing_server(count: usize) -> String {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let mut queries = Vec::new();
+            let mut buf = [0u8; 512];
+            for _ in 0..count {
+                let (received, from) = socket.recv_from(&mut buf).unwrap();
+                queries.push((buf[..received].to_vec(), from));
+            }
+            for (mut query, from) in queries.into_iter().rev() {
+                query[2] |= 0x80; // QR
+                let mut wrong = query.clone();
+                wrong[HEADER_LEN + 1] ^= 0x01;
+                socket.send_to(&wrong, from).unwrap();
+                socket.send_to(&query, from).unwrap();
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn transport_demultiplexes() {
+        let server = reversing_server(3);
+        let engine = Arc::new(Engine::new(Duration::from_secs(5)).unwrap());
+        let names = ["a.example", "b.example", "c.example"];
+
+        let handles: Vec<_> = names
+            .iter()
+            .map(|&name| {
+                let (engine, server) = (Arc::clone(&engine), server.clone());
+                thread::spawn(move || engine.lookup(name.to_string(), "A".to_string(), &server))
+            })
+            .collect();
+        for (name, handle) in names.iter().zip(handles) {
+            let reply = handle.join().unwrap().unwrap();
+            assert_eq!(reply.message.question[0].qname[0], name[..1]);
+            assert_eq!(reply.server, server);
+        }
+        assert!(engine.pending.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn transport_times_out() {
+        // a server that never answers
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let engine = Engine::new(Duration::from_millis(50)).unwrap();
+        let err = engine
+            .exchange(
+                "example".to_string(),
+                "A".to_string(),
+                &silent.local_addr().unwrap().to_string(),
+            )
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(engine.pending.lock().unwrap().is_empty());
+    }
+}
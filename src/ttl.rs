@@ -0,0 +1,137 @@
+/* How long a record may be cached, in seconds. */
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Ttl(u32);
+
+impl Ttl {
+    pub const fn from_secs(secs: u32) -> Ttl {
+        Ttl(secs)
+    }
+
+    /// A TTL as read from the wire.  Values with the top bit set are taken
+    /// to be zero (RFC 2181 §8).
+    pub fn from_wire(value: u32) -> Ttl {
+        if value > i32::MAX as u32 {
+            Ttl(0)
+        } else {
+            Ttl(value)
+        }
+    }
+
+    pub fn as_secs(self) -> u32 {
+        self.0
+    }
+
+    pub fn as_duration(self) -> Duration {
+        Duration::from_secs(self.0 as u64)
+    }
+}
+
+impl From<u32> for Ttl {
+    fn from(secs: u32) -> Ttl {
+        Ttl(secs)
+    }
+}
+
+/// Parse a TTL, either as plain seconds or in the BIND style with
+/// units, e.g. `1h30m` or `2W`.
+fn parse_ttl(token: &str) -> Option<u32> {
+    if let Ok(seconds) = token.parse::<u32>() {
+        return Some(seconds);
+    }
+    let mut total: u32 = 0;
+    let mut value: Option<u32> = None;
+    for c in token.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            value = Some(value.unwrap_or(0).checked_mul(10)?.checked_add(digit)?);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 604_800,
+            _ => return None,
+        };
+        total = total.checked_add(value.take()?.checked_mul(unit)?)?;
+    }
+    match value {
+        Some(_) => None, // trailing digits without a unit
+        None => Some(total),
+    }
+}
+
+/// Accepts plain seconds or BIND style units, e.g. `1h30m`.
+impl FromStr for Ttl {
+    type Err = ();
+    fn from_str(text: &str) -> Result<Ttl, ()> {
+        parse_ttl(text).map(Ttl).ok_or(())
+    }
+}
+
+/// In weeks, days, hours, minutes and seconds, leaving out the units that
+/// are zero: `1h30m`, `1d`, `0s`.
+impl fmt::Display for Ttl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0 == 0 {
+            return write!(f, "0s");
+        }
+        let mut rest = self.0;
+        for &(unit, secs) in [
+            ("w", 604_800),
+            ("d", 86400),
+            ("h", 3600),
+            ("m", 60),
+            ("s", 1),
+        ]
+        .iter()
+        {
+            if rest >= secs {
+                write!(f, "{}{}", rest / secs, unit)?;
+                rest %= secs;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ttl_display() {
+        for &(secs, text) in [
+            (0, "0s"),
+            (59, "59s"),
+            (5400, "1h30m"),
+            (86400, "1d"),
+            (694_861, "1w1d1h1m1s"),
+        ]
+        .iter()
+        {
+            assert_eq!(Ttl::from_secs(secs).to_string(), text);
+            assert_eq!(text.parse::<Ttl>(), Ok(Ttl::from_secs(secs)));
+        }
+    }
+
+    #[test]
+    fn ttl_units() {
+        assert_eq!(Ttl::from_str("3600"), Ok(Ttl::from_secs(3600)));
+        assert_eq!(Ttl::from_str("1h30m"), Ok(Ttl::from_secs(5400)));
+        assert_eq!(Ttl::from_str("1W2d"), Ok(Ttl::from_secs(777_600)));
+        assert!(Ttl::from_str("10x").is_err());
+        assert!(Ttl::from_str("1h5").is_err());
+    }
+
+    #[test]
+    fn ttl_from_wire() {
+        assert_eq!(Ttl::from_wire(0x7fff_ffff).as_secs(), 0x7fff_ffff);
+        assert_eq!(Ttl::from_wire(0x8000_0000), Ttl::from_secs(0));
+    }
+}
@@ -1,5 +1,8 @@
 use crate::errors::ParseError;
 use std::error;
+use std::net::Ipv6Addr;
+
+pub const TRUNCATED: &dyn error::Error = &ParseError::Truncated;
 
 pub fn byte_combine(a: u8, b: u8) -> u16 {
     ((a as u16) << 8) | b as u16
@@ -9,41 +12,379 @@ pub fn bytes_to_name_offset(a: u8, b: u8) -> usize {
     (byte_combine(a, b) & 0b11_1111_1111_1111) as usize
 }
 
+/// The 32 hex nibbles of an IPv6 address, most significant first, as used
+/// by ip6.arpa names and the SPF "i" macro.
+pub fn ipv6_nibbles(ip: Ipv6Addr) -> Vec<String> {
+    ip.octets()
+        .iter()
+        .flat_map(|b| vec![format!("{:x}", b >> 4), format!("{:x}", b & 0xf)])
+        .collect()
+}
+
+/// Join labels into a fully qualified name with its trailing dot.
+pub fn fqdn(name: &[String]) -> String {
+    let mut fqdn = name.join(".");
+    if !fqdn.ends_with('.') {
+        fqdn.push('.');
+    }
+    fqdn
+}
+
+/// Encode a name as uncompressed wire labels, terminated by the root label.
+pub fn name_to_wire(name: &[String]) -> Vec<u8> {
+    let mut wire = Vec::new();
+    for label in name.iter().filter(|l| !l.is_empty()) {
+        wire.push(label.len() as u8);
+        wire.extend_from_slice(label.as_bytes());
+    }
+    wire.push(0);
+    wire
+}
+
+/// Read a <character-string>: a length octet followed by that many octets.
+pub fn extract_character_string(
+    bytes: &[u8],
+    offset: usize,
+) -> Result<(Vec<u8>, usize), &'static dyn error::Error> {
+    let length = *bytes.get(offset).ok_or(TRUNCATED)? as usize;
+    let start = offset + 1;
+    let string = bytes.get(start..start + length).ok_or(TRUNCATED)?;
+    Ok((string.to_owned(), start + length))
+}
+
+/// Format a <character-string> in master file notation: quoted, with `"`
+/// and `\` escaped and non-printable octets written as `\DDD`.
+pub fn fmt_character_string(string: &[u8]) -> String {
+    let mut out = String::from("\"");
+    for &byte in string.iter() {
+        match byte {
+            b'"' | b'\\' => {
+                out.push('\\');
+                out.push(byte as char);
+            }
+            0x20..=0x7e => out.push(byte as char),
+            _ => out.push_str(&format!("\\{:03}", byte)),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub fn hex_encode(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+pub fn hex_decode(text: &str) -> Result<Vec<u8>, ParseError> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return Err(ParseError::InvalidRData);
+    }
+    (0..text.len() / 2)
+        .map(|i| u8::from_str_radix(&text[2 * i..2 * i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| ParseError::InvalidRData)
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Base64 encoding (RFC 4648 §4) with padding, as used in presentation format.
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+pub fn base64_decode(text: &str) -> Result<Vec<u8>, ParseError> {
+    let text = text.trim_end_matches('=');
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = BASE64_ALPHABET
+            .iter()
+            .position(|&a| a == c)
+            .ok_or(ParseError::InvalidRData)?;
+        acc = acc << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+/// Resolve master file escapes (RFC 1035 §5.1): `\DDD` is the octet with
+/// decimal value DDD and `\X` is the character X taken literally.
+pub fn unescape(text: &str) -> Result<Vec<u8>, ParseError> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        if i + 1 >= bytes.len() {
+            return Err(ParseError::InvalidEscape);
+        }
+        if bytes[i + 1].is_ascii_digit() {
+            if i + 3 >= bytes.len() {
+                return Err(ParseError::InvalidEscape);
+            }
+            let digits = &bytes[i + 1..i + 4];
+            if !digits.iter().all(u8::is_ascii_digit) {
+                return Err(ParseError::InvalidEscape);
+            }
+            let value = digits
+                .iter()
+                .fold(0u16, |acc, d| acc * 10 + (d - b'0') as u16);
+            if value > 255 {
+                return Err(ParseError::InvalidEscape);
+            }
+            out.push(value as u8);
+            i += 4;
+        } else {
+            out.push(bytes[i + 1]);
+            i += 2;
+        }
+    }
+    Ok(out)
+}
+
+/// Parse a domain name in master file notation. Names without a trailing
+/// dot are relative to `origin`, and `@` stands for the origin itself.
+pub fn parse_presentation_name(text: &str, origin: &[String]) -> Result<Vec<String>, ParseError> {
+    if text == "@" {
+        return Ok(origin.to_vec());
+    }
+    if text == "." {
+        return Ok(Vec::new());
+    }
+
+    let mut raw_labels = Vec::new();
+    let mut current = String::new();
+    let mut absolute = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push(c);
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            '.' => {
+                if current.is_empty() {
+                    return Err(ParseError::InvalidName);
+                }
+                raw_labels.push(current);
+                current = String::new();
+                if chars.peek().is_none() {
+                    absolute = true;
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        raw_labels.push(current);
+    }
+
+    let mut name = Vec::new();
+    for raw in raw_labels.iter() {
+        let label = unescape(raw).map_err(|_| ParseError::InvalidName)?;
+        if label.len() > 63 {
+            return Err(ParseError::InvalidName);
+        }
+        name.push(String::from_utf8(label).map_err(|_| ParseError::InvalidName)?);
+    }
+    if !absolute {
+        name.extend(origin.iter().filter(|l| !l.is_empty()).cloned());
+    }
+    Ok(name)
+}
+
+/// The most octets a name may take on the wire, length octets included
+/// (RFC 1035 §3.1).
+const MAX_NAME_LEN: usize = 255;
+/// How many compression pointers to follow in one name.  Every pointer
+/// must point backwards, so a longer chain can only be a loop or an attempt
+/// to make us do busy work.
+const MAX_POINTER_HOPS: usize = 32;
+
+/// Decode the name starting at `offset`, following compression pointers,
+/// and return it with the offset just past it.  Every read is checked
+/// against the end of `bytes`.
 pub fn extract_name(
     bytes: &[u8],
-    mut offset: usize,
+    offset: usize,
 ) -> Result<(Vec<String>, usize), &dyn error::Error> {
     let mut name = Vec::new();
+    let mut name_len = 0;
+    let mut position = offset;
+    // where the name ends in the buffer: after its first pointer, if any
+    let mut end = None;
+    let mut hops = 0;
     loop {
-        let label_len = bytes[offset] as usize;
+        let label_len = *bytes.get(position).ok_or(TRUNCATED)? as usize;
+        match label_len >> 6 {
+            0 => {
+                name_len += 1 + label_len;
+                if name_len > MAX_NAME_LEN {
+                    return Err(&ParseError::NameTooLong);
+                }
+                if label_len == 0 {
+                    position += 1;
+                    break;
+                }
+                let label = bytes
+                    .get(position + 1..position + 1 + label_len)
+                    .ok_or(TRUNCATED)?;
+                let label = std::str::from_utf8(label)
+                    .map_err(|_| &ParseError::InvalidName as &dyn error::Error)?;
+                name.push(label.to_string());
+                position += 1 + label_len;
+            }
+            3 => {
+                let low = *bytes.get(position + 1).ok_or(TRUNCATED)?;
+                let name_offset = bytes_to_name_offset(bytes[position], low);
+                if name_offset >= position {
+                    return Err(&ParseError::PointerForward);
+                }
+                hops += 1;
+                if hops > MAX_POINTER_HOPS {
+                    return Err(&ParseError::PointerLoop);
+                }
+                end.get_or_insert(position + 2);
+                position = name_offset;
+            }
+            // 0b01 and 0b10 are extended label types (RFC 6891 §5), unused
+            _ => return Err(&ParseError::InvalidLabelType),
+        }
+    }
+    Ok((name, end.unwrap_or(position)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if label_len == 0 {
-            offset += 1;
-            break;
+    #[test]
+    fn base64_round_trip() {
+        for (data, encoded) in [
+            (&b""[..], ""),
+            (&b"f"[..], "Zg=="),
+            (&b"fo"[..], "Zm8="),
+            (&b"foo"[..], "Zm9v"),
+            (&b"foobar"[..], "Zm9vYmFy"),
+        ]
+        .iter()
+        {
+            assert_eq!(base64_encode(data), *encoded);
+            assert_eq!(base64_decode(encoded).unwrap(), data.to_vec());
         }
-        if (label_len >> 6) == 3 {
-            let name_offset = bytes_to_name_offset(bytes[offset], bytes[offset + 1]);
-            if name_offset > offset {
-                return Err(&ParseError::PointerForward);
-            }
-            let (mut ref_name, _loffset) = extract_name(bytes, name_offset)?;
-            name.append(&mut ref_name);
-            break;
+        assert!(base64_decode("Zm9v!").is_err());
+    }
+
+    #[test]
+    fn extract_names() {
+        let wire = b"\x03www\x07example\x00\x04mail\xc0\x04\xc0\x0d";
+        let (name, end) = extract_name(wire, 0).unwrap();
+        assert_eq!((name.join("."), end), ("www.example".to_string(), 13));
+        let (name, end) = extract_name(wire, 13).unwrap();
+        assert_eq!((name.join("."), end), ("mail.example".to_string(), 20));
+        // a pointer to a pointer
+        let (name, end) = extract_name(wire, 20).unwrap();
+        assert_eq!((name.join("."), end), ("mail.example".to_string(), 22));
+    }
+
+    #[test]
+    fn extract_name_errors() {
+        let error = |wire: &[u8], offset| extract_name(wire, offset).err().unwrap().to_string();
+        assert_eq!(error(b"\x03ww", 0), "Truncated");
+        assert_eq!(error(b"\x03www", 0), "Truncated");
+        assert_eq!(error(b"\xc0", 0), "Truncated");
+        assert_eq!(error(b"\xc0\x00", 0), "PointerForward");
+        assert_eq!(error(b"\x00\xc0\x05", 1), "PointerForward");
+        assert_eq!(error(b"\x40", 0), "InvalidLabelType");
+        assert_eq!(error(b"\x01\xff\x00", 0), "InvalidName");
+        // "a" followed by a pointer back to itself
+        assert_eq!(error(b"\x01a\xc0\x00", 0), "PointerLoop");
+
+        // the root, then a chain of pointers, each to the one before
+        let mut chain = vec![0u8];
+        for i in 0..40u16 {
+            chain.extend_from_slice(&(0xc000 | (i * 2).saturating_sub(1)).to_be_bytes());
         }
-        if label_len > 63 {
-            panic!("omg {:b}", label_len >> 6);
+        assert_eq!(extract_name(&chain, 63).unwrap(), (Vec::new(), 65));
+        assert_eq!(error(&chain, 65), "PointerLoop");
+
+        let mut long = Vec::new();
+        for _ in 0..4 {
+            long.push(63);
+            long.extend_from_slice(&[b'a'; 63]);
         }
+        long.push(0);
+        assert_eq!(error(&long, 0), "NameTooLong");
+        assert_eq!(extract_name(&long[64..], 0).unwrap().0.len(), 3);
+    }
 
-        let label_start = 1 + offset;
-        let label_end = label_start + label_len;
-        let label = match std::str::from_utf8(&bytes[label_start..label_end]) {
-            Ok(v) => v.to_string(),
-            Err(e) => {
-                panic!("invalid utf8 label {}; label_len {}", e, label_len);
-            }
+    #[test]
+    fn extract_name_random_input() {
+        // a fixed xorshift sequence, so that failures reproduce
+        let mut state: u32 = 0x2545_f491;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
         };
-        name.push(label);
-        offset += 1 + label_len;
+        for _ in 0..10_000 {
+            let len = next() as usize % 64;
+            let mut wire: Vec<u8> = (0..len).map(|_| next() as u8).collect();
+            // make pointers and short labels common
+            for byte in wire.iter_mut() {
+                if *byte & 0x30 == 0 {
+                    *byte |= 0xc0;
+                } else {
+                    *byte &= 0x0f;
+                }
+            }
+            for offset in 0..=len {
+                let _ = extract_name(&wire, offset);
+            }
+        }
+    }
+
+    #[test]
+    fn presentation_names() {
+        let origin = vec!["example".to_string(), "com".to_string()];
+        assert_eq!(parse_presentation_name("@", &origin).unwrap(), origin);
+        assert_eq!(
+            parse_presentation_name("www", &origin).unwrap().join("."),
+            "www.example.com"
+        );
+        assert_eq!(
+            parse_presentation_name("a\\.b.example.", &origin).unwrap(),
+            vec!["a.b".to_string(), "example".to_string()]
+        );
+        assert_eq!(parse_presentation_name("\\065bc", &[]).unwrap(), vec!["Abc"]);
+        assert!(parse_presentation_name("a..b", &origin).is_err());
     }
-    Ok((name, offset))
 }
@@ -0,0 +1,456 @@
+/* A reader for master files, the zone file format of RFC 1035 §5.
+ *
+ * Supports the $ORIGIN, $TTL (RFC 2308) and $INCLUDE directives,
+ * parenthesised continuation lines, comments, quoted strings, relative
+ * names, omitted owners, TTLs and classes, and \X / \DDD escapes.
+ */
+
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::rdata::RData;
+use crate::rr::{Class, RRType, RR};
+use crate::ttl::Ttl;
+use crate::utils::parse_presentation_name;
+
+#[derive(Debug, Clone)]
+pub struct ZoneError {
+    pub line: usize,
+    pub reason: String,
+}
+
+impl ZoneError {
+    fn new(line: usize, reason: &str) -> ZoneError {
+        ZoneError {
+            line,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl error::Error for ZoneError {
+    fn description(&self) -> &str {
+        &self.reason
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        None
+    }
+}
+
+impl fmt::Display for ZoneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+// One logical entry, possibly spanning several physical lines.
+struct Entry {
+    line: usize,
+    blank_owner: bool,
+    tokens: Vec<String>,
+}
+
+fn finish_token(current: &mut Option<String>, tokens: &mut Vec<String>) {
+    if let Some(token) = current.take() {
+        tokens.push(token);
+    }
+}
+
+/* Split master file text into entries of tokens. Escapes are left in the
+ * tokens, since whether "\." separates labels depends on the field. */
+fn tokenize(text: &str) -> Result<Vec<Entry>, ZoneError> {
+    let mut entries = Vec::new();
+    let mut tokens = Vec::new();
+    let mut current: Option<String> = None;
+    let mut line = 1;
+    let mut entry_line = 1;
+    let mut depth = 0;
+    let mut in_quote = false;
+    let mut line_start = true;
+    let mut blank_owner = false;
+
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if tokens.is_empty() && current.is_none() {
+            entry_line = line;
+        }
+        if in_quote {
+            let token = current.get_or_insert_with(String::new);
+            match c {
+                '"' => {
+                    in_quote = false;
+                    finish_token(&mut current, &mut tokens);
+                }
+                '\\' => {
+                    token.push(c);
+                    match chars.next() {
+                        Some(escaped) => token.push(escaped),
+                        None => return Err(ZoneError::new(line, "unterminated escape")),
+                    }
+                }
+                '\n' => return Err(ZoneError::new(line, "unterminated quoted string")),
+                _ => token.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '\n' => {
+                finish_token(&mut current, &mut tokens);
+                line += 1;
+                if depth == 0 {
+                    if !tokens.is_empty() {
+                        entries.push(Entry {
+                            line: entry_line,
+                            blank_owner,
+                            tokens: tokens.split_off(0),
+                        });
+                    }
+                    line_start = true;
+                    blank_owner = false;
+                }
+                continue;
+            }
+            ' ' | '\t' | '\r' => {
+                if line_start && depth == 0 {
+                    blank_owner = true;
+                }
+                finish_token(&mut current, &mut tokens);
+            }
+            ';' => {
+                finish_token(&mut current, &mut tokens);
+                // leave the newline to end the entry
+                let rest = chars.as_str();
+                let skip = rest.find('\n').unwrap_or(rest.len());
+                chars = rest[skip..].chars();
+            }
+            '(' => {
+                finish_token(&mut current, &mut tokens);
+                depth += 1;
+            }
+            ')' => {
+                finish_token(&mut current, &mut tokens);
+                if depth == 0 {
+                    return Err(ZoneError::new(line, "unbalanced parentheses"));
+                }
+                depth -= 1;
+            }
+            '"' => {
+                // a quoted string straight after other text continues that
+                // token, as in the SVCB parameter alpn="h2,h3"
+                current.get_or_insert_with(String::new);
+                in_quote = true;
+            }
+            '\\' => {
+                let token = current.get_or_insert_with(String::new);
+                token.push(c);
+                match chars.next() {
+                    Some(escaped) => token.push(escaped),
+                    None => return Err(ZoneError::new(line, "unterminated escape")),
+                }
+            }
+            _ => current.get_or_insert_with(String::new).push(c),
+        }
+        line_start = false;
+    }
+
+    if in_quote {
+        return Err(ZoneError::new(line, "unterminated quoted string"));
+    }
+    if depth != 0 {
+        return Err(ZoneError::new(line, "unbalanced parentheses"));
+    }
+    finish_token(&mut current, &mut tokens);
+    if !tokens.is_empty() {
+        entries.push(Entry {
+            line: entry_line,
+            blank_owner,
+            tokens,
+        });
+    }
+    Ok(entries)
+}
+
+/// A type mnemonic, or TYPE<n> for any type (RFC 3597 §5); the RDATA of
+/// types without a mnemonic has to be given as `\# <length> <hex>`.
+fn parse_type(token: &str) -> Option<RRType> {
+    RRType::try_from(token.to_uppercase()).ok()
+}
+
+pub struct ZoneParser {
+    origin: Vec<String>,
+    base_dir: PathBuf,
+    default_ttl: Option<Ttl>,
+    last_ttl: Option<Ttl>,
+    last_owner: Option<Vec<String>>,
+    last_class: Class,
+    records: Vec<RR>,
+}
+
+impl ZoneParser {
+    pub fn new(origin: &str) -> Result<ZoneParser, ZoneError> {
+        let origin = parse_presentation_name(origin, &[])
+            .map_err(|_| ZoneError::new(0, "invalid origin"))?;
+        Ok(ZoneParser {
+            origin,
+            base_dir: PathBuf::from("."),
+            default_ttl: None,
+            last_ttl: None,
+            last_owner: None,
+            last_class: Class::IN,
+            records: Vec::new(),
+        })
+    }
+
+    /// Parse master file text; `$INCLUDE` paths are taken relative to the
+    /// current directory.
+    pub fn parse_str(mut self, text: &str) -> Result<Vec<RR>, ZoneError> {
+        self.read(text)?;
+        Ok(self.records)
+    }
+
+    /// Parse a master file; `$INCLUDE` paths are taken relative to the
+    /// directory containing it.
+    pub fn parse_file<P: AsRef<Path>>(mut self, path: P) -> Result<Vec<RR>, ZoneError> {
+        self.include(path.as_ref(), 0)?;
+        Ok(self.records)
+    }
+
+    fn include(&mut self, path: &Path, line: usize) -> Result<(), ZoneError> {
+        let path = self.base_dir.join(path);
+        let text = fs::read_to_string(&path)
+            .map_err(|e| ZoneError::new(line, &format!("{}: {}", path.display(), e)))?;
+        let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let saved_dir = std::mem::replace(&mut self.base_dir, parent);
+        let result = self.read(&text);
+        self.base_dir = saved_dir;
+        result
+    }
+
+    fn read(&mut self, text: &str) -> Result<(), ZoneError> {
+        for entry in tokenize(text)? {
+            if !entry.blank_owner && entry.tokens[0].starts_with('$') {
+                self.directive(&entry)?;
+            } else {
+                let rr = self.record(&entry)?;
+                self.records.push(rr);
+            }
+        }
+        Ok(())
+    }
+
+    fn directive(&mut self, entry: &Entry) -> Result<(), ZoneError> {
+        let tokens = &entry.tokens;
+        let argument = || {
+            tokens
+                .get(1)
+                .ok_or_else(|| ZoneError::new(entry.line, "directive without argument"))
+        };
+        match tokens[0].to_uppercase().as_ref() {
+            "$ORIGIN" => {
+                self.origin = parse_presentation_name(argument()?, &self.origin)
+                    .map_err(|e| ZoneError::new(entry.line, &e.to_string()))?;
+            }
+            "$TTL" => {
+                let ttl = Ttl::from_str(argument()?)
+                    .map_err(|_| ZoneError::new(entry.line, "invalid TTL"))?;
+                self.default_ttl = Some(ttl);
+            }
+            "$INCLUDE" => {
+                let path = PathBuf::from(argument()?);
+                let saved_origin = self.origin.clone();
+                if let Some(origin) = tokens.get(2) {
+                    self.origin = parse_presentation_name(origin, &self.origin)
+                        .map_err(|e| ZoneError::new(entry.line, &e.to_string()))?;
+                }
+                let result = self.include(&path, entry.line);
+                self.origin = saved_origin;
+                result?;
+            }
+            directive => {
+                return Err(ZoneError::new(
+                    entry.line,
+                    &format!("unknown directive {}", directive),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, entry: &Entry) -> Result<RR, ZoneError> {
+        let tokens = &entry.tokens;
+        let line = entry.line;
+        let mut idx = 0;
+
+        let owner = if entry.blank_owner {
+            self.last_owner
+                .clone()
+                .ok_or_else(|| ZoneError::new(line, "no previous owner name"))?
+        } else {
+            idx += 1;
+            parse_presentation_name(&tokens[0], &self.origin)
+                .map_err(|e| ZoneError::new(line, &e.to_string()))?
+        };
+
+        // TTL and class may appear in either order, and either may be omitted
+        let mut ttl = None;
+        let mut class = None;
+        while idx < tokens.len() {
+            let token = &tokens[idx];
+            if ttl.is_none() && token.starts_with(|c: char| c.is_ascii_digit()) {
+                ttl = Some(Ttl::from_str(token).map_err(|_| ZoneError::new(line, "invalid TTL"))?);
+            } else if class.is_none() && Class::try_from(token.to_string()).is_ok() {
+                class = Class::try_from(token.to_string()).ok();
+            } else {
+                break;
+            }
+            idx += 1;
+        }
+
+        let rrtype = tokens
+            .get(idx)
+            .and_then(|t| parse_type(t))
+            .ok_or_else(|| ZoneError::new(line, "missing or unknown type"))?;
+        idx += 1;
+
+        let ttl = match ttl {
+            Some(ttl) => {
+                self.last_ttl = Some(ttl);
+                ttl
+            }
+            None => self
+                .default_ttl
+                .or(self.last_ttl)
+                .ok_or_else(|| ZoneError::new(line, "no TTL and no $TTL default"))?,
+        };
+        let class = class.unwrap_or(self.last_class);
+        self.last_class = class;
+        self.last_owner = Some(owner.clone());
+
+        let rdata = RData::from_presentation(rrtype, &tokens[idx..], &self.origin)
+            .map_err(|e| ZoneError::new(line, &format!("{} for {}", e, rrtype)))?;
+        Ok(RR::new(owner, rrtype, class, ttl, rdata))
+    }
+}
+
+/// Parse master file text, with relative names completed by `origin`.
+pub fn parse_str(text: &str, origin: &str) -> Result<Vec<RR>, ZoneError> {
+    ZoneParser::new(origin)?.parse_str(text)
+}
+
+/// Read and parse a master file, with relative names completed by `origin`.
+pub fn parse_file<P: AsRef<Path>>(path: P, origin: &str) -> Result<Vec<RR>, ZoneError> {
+    ZoneParser::new(origin)?.parse_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static ZONE: &str = r#"
+$ORIGIN example.com.
+$TTL 1h
+@   IN  SOA ns1 hostmaster (
+            2020022401 ; serial
+            7200       ; refresh
+            3600 1209600
+            300 )
+    IN  NS  ns1
+    IN  NS  ns2.example.net.
+ns1 300 A   192.0.2.1
+    IN 600 AAAA 2001:db8::1
+www CNAME   @
+mail    MX  10 mx\.relay.example.net.
+txt TXT "v=spf1 -all" "two\032words\"" plain
+$ORIGIN sub
+host    A   198.51.100.7
+"#;
+
+    #[test]
+    fn zone_parse() {
+        let records = parse_str(ZONE, ".").unwrap();
+        assert_eq!(records.len(), 9);
+
+        let soa = &records[0];
+        assert_eq!(soa.name(), &["example".to_string(), "com".to_string()]);
+        assert_eq!(soa.ttl(), Ttl::from_secs(3600));
+        assert_eq!(
+            soa.rdata().to_presentation(),
+            "ns1.example.com. hostmaster.example.com. 2020022401 7200 3600 1209600 300"
+        );
+
+        // omitted owner and class carry over from the previous entry
+        assert_eq!(records[1].name(), soa.name());
+        assert_eq!(records[2].rdata().to_presentation(), "ns2.example.net.");
+
+        assert_eq!(records[3].ttl(), Ttl::from_secs(300));
+        assert_eq!(records[4].ttl(), Ttl::from_secs(600));
+        assert_eq!(records[4].name()[0], "ns1");
+        assert_eq!(records[4].rdata().to_presentation(), "2001:db8::1");
+
+        assert_eq!(records[5].rdata().to_presentation(), "example.com.");
+        assert_eq!(records[6].rdata().to_wire()[..4], [0, 10, 8, b'm']);
+        assert_eq!(
+            records[7].rdata().to_wire(),
+            b"\x0bv=spf1 -all\x0atwo words\"\x05plain".to_vec()
+        );
+
+        assert_eq!(records[8].name().join("."), "host.sub.example.com");
+        assert_eq!(records[8].rdata().to_presentation(), "198.51.100.7");
+    }
+
+    #[test]
+    fn zone_quoted_svcb_values() {
+        let text = "svc 300 HTTPS 1 . alpn=\"h2,h3\" key65000= port=443 key667=\"a b\"\n";
+        let records = parse_str(text, "example.").unwrap();
+        assert_eq!(
+            records[0].rdata().to_presentation(),
+            "1 . alpn=h2,h3 port=443 key667=\"a b\" key65000=\"\""
+        );
+    }
+
+    #[test]
+    fn zone_generic_types() {
+        let text = "$TTL 300\n\
+                    @ TYPE43 \\# 4 deadbeef\n\
+                    www type1 \\# 4 c0000201\n";
+        let records = parse_str(text, "example.").unwrap();
+        assert_eq!(records[0].rrtype(), RRType::Unknown(43));
+        assert_eq!(records[0].rdata(), &RData::UNKNOWN(43, vec![0xde, 0xad, 0xbe, 0xef]));
+        assert_eq!(records[0].rdata().to_presentation(), "\\# 4 deadbeef");
+        assert_eq!(records[1].rrtype(), RRType::A);
+        assert_eq!(records[1].rdata().to_presentation(), "192.0.2.1");
+        assert!(parse_str("@ 300 TYPE65536 \\# 0\n", "example.").is_err());
+    }
+
+    #[test]
+    fn zone_include() {
+        let dir = std::env::temp_dir().join(format!("lud-zone-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("hosts.inc"), "a 60 A 192.0.2.10\n").unwrap();
+        fs::write(
+            dir.join("main.zone"),
+            "$INCLUDE hosts.inc lab.example.\nb 60 A 192.0.2.11\n",
+        )
+        .unwrap();
+
+        let records = parse_file(dir.join("main.zone"), "example.").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(records[0].name().join("."), "a.lab.example");
+        assert_eq!(records[1].name().join("."), "b.example");
+    }
+
+    #[test]
+    fn zone_errors() {
+        let err = parse_str("a 60 A 192.0.2.1\nb 60 A (\n", "example.").err().unwrap();
+        assert_eq!(err.line, 3);
+        let err = parse_str("a A 192.0.2.1\n", "example.").err().unwrap();
+        assert_eq!(err.reason, "no TTL and no $TTL default");
+        assert!(parse_str("a 60 A 192.0.2.300\n", "example.").is_err());
+    }
+}
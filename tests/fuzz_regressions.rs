@@ -0,0 +1,49 @@
+extern crate lud;
+
+use std::fs;
+
+use lud::message::Message;
+use lud::question::Question;
+use lud::rr::RR;
+
+/// The inputs kept for a fuzz target: ones that once crashed it.
+fn regressions(target: &str) -> Vec<(String, Vec<u8>)> {
+    let mut inputs: Vec<(String, Vec<u8>)> = fs::read_dir(format!("fuzz/regressions/{}", target))
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, fs::read(&path).unwrap())
+        })
+        .collect();
+    inputs.sort();
+    assert!(!inputs.is_empty());
+    inputs
+}
+
+#[test]
+fn message_regressions() {
+    for (name, data) in regressions("message") {
+        if let Ok(message) = Message::from_wire(&data) {
+            // the round_trip target's property
+            let wire = message.to_wire();
+            let reparsed = Message::from_wire(&wire)
+                .unwrap_or_else(|e| panic!("{}: reparsing failed: {}", name, e));
+            assert_eq!(reparsed, message, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn rr_regressions() {
+    for (_, data) in regressions("rr") {
+        let _ = RR::from_wire(&data, 0);
+    }
+}
+
+#[test]
+fn question_regressions() {
+    for (_, data) in regressions("question") {
+        let _ = Question::from_wire(&data, 0);
+    }
+}
@@ -2,6 +2,10 @@
 extern crate lud;
 
 use lud::message::Message;
+use lud::rdata::RData;
+use lud::rr::{Class, RRType, RR};
+use lud::ttl::Ttl;
+use lud::zone;
 
 // TODO: more of these and with more detail
 #[test]
@@ -26,4 +30,31 @@ fn test_parse_message_A() {
     assert_eq!(parsed.ancount, 1);
     assert_eq!(parsed.nscount, 0);
     assert_eq!(parsed.arcount, 0);
+
+    let name = vec!["example".to_string(), "com".to_string()];
+    let address = RData::from_presentation(RRType::A, &["93.184.216.34".to_string()], &[]).unwrap();
+    assert_eq!(
+        parsed.answer,
+        vec![RR::new(
+            name,
+            RRType::A,
+            Class::IN,
+            Ttl::from_secs(7230),
+            address
+        )]
+    );
+    assert_eq!(parsed.to_wire(), msg.to_vec());
+    assert_eq!(Message::from_wire(&parsed.to_wire()).unwrap(), parsed);
+}
+
+#[test]
+fn test_zone_fixture() {
+    let records = zone::parse_file("tests/fixtures/example.com.zone", ".").unwrap();
+    assert_eq!(records.len(), 7);
+    assert!(records.iter().all(|rr| rr.ttl().as_secs() == 86400));
+    assert_eq!(
+        records[4].rdata().to_presentation(),
+        "2606:2800:220:1:248:1893:25c8:1946"
+    );
+    assert_eq!(records[6].name().join("."), "www.example.com");
 }